@@ -0,0 +1,114 @@
+use serde_json::Value;
+
+use crate::confirm::CONFIRMED_TOOLS;
+use crate::guard::GUARDED_TOOLS;
+
+/// Write tools that may lose data or disrupt running guests, besides those
+/// that need confirmation or are guarded.
+pub const DESTRUCTIVE_TOOLS: &[&str] = &[
+    "stop_vm",
+    "stop_container",
+    "restore_backup",
+    "delete_firewall_rule",
+    "vm_exec",
+    "vm_write_file",
+    "manage_service",
+    "join_cluster",
+];
+
+/// Write tools that have no further effect when repeated with the same
+/// arguments.
+pub const IDEMPOTENT_TOOLS: &[&str] = &[
+    "start_vm",
+    "start_container",
+    "stop_vm",
+    "stop_container",
+    "shutdown_vm",
+    "shutdown_container",
+    "set_tags",
+    "add_tag",
+    "remove_tag",
+    "set_vm_cloudinit",
+    "set_subscription_key",
+    "update_vm_resources",
+    "update_container_resources",
+    "update_storage",
+    "update_pool",
+    "update_role",
+    "update_acl",
+    "update_ha_resource",
+    "update_replication_job",
+    "delete_vm",
+    "delete_container",
+    "delete_snapshot",
+    "delete_storage",
+    "delete_pool",
+    "delete_user",
+    "delete_role",
+    "delete_replication_job",
+    "remove_ha_resource",
+    "vm_write_file",
+];
+
+/// Tools that reach beyond the cluster: the internet, or whatever runs
+/// inside a guest.
+pub const OPEN_WORLD_TOOLS: &[&str] = &[
+    "download_url",
+    "run_apt_update",
+    "check_subscription",
+    "set_subscription_key",
+    "vm_exec",
+    "vm_write_file",
+    "vm_read_file",
+    "join_cluster",
+];
+
+/// Fills in `destructiveHint`, `idempotentHint` and `openWorldHint` of a
+/// tool definition from its `readOnlyHint` and the tables above. Reads are
+/// never destructive and always idempotent.
+pub fn annotate(tool: &mut Value) {
+    let name = tool["name"].as_str().unwrap_or_default();
+    let read_only = tool["annotations"]["readOnlyHint"] == true;
+    let destructive = !read_only
+        && (CONFIRMED_TOOLS.contains(&name)
+            || GUARDED_TOOLS.contains(&name)
+            || DESTRUCTIVE_TOOLS.contains(&name));
+    let idempotent = read_only || IDEMPOTENT_TOOLS.contains(&name);
+    let open_world = OPEN_WORLD_TOOLS.contains(&name);
+
+    let annotations = &mut tool["annotations"];
+    annotations["destructiveHint"] = destructive.into();
+    annotations["idempotentHint"] = idempotent.into();
+    annotations["openWorldHint"] = open_world.into();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn annotated(name: &str, read_only: bool) -> Value {
+        let mut tool = json!({ "name": name, "annotations": { "readOnlyHint": read_only } });
+        annotate(&mut tool);
+        tool["annotations"].clone()
+    }
+
+    #[test]
+    fn test_annotate() {
+        let list = annotated("list_vms", true);
+        assert_eq!(list["destructiveHint"], false);
+        assert_eq!(list["idempotentHint"], true);
+        assert_eq!(list["openWorldHint"], false);
+
+        let delete = annotated("delete_vm", false);
+        assert_eq!(delete["readOnlyHint"], false);
+        assert_eq!(delete["destructiveHint"], true);
+        assert_eq!(delete["idempotentHint"], true);
+
+        let create = annotated("create_vm", false);
+        assert_eq!(create["destructiveHint"], false);
+        assert_eq!(create["idempotentHint"], false);
+
+        assert_eq!(annotated("download_url", false)["openWorldHint"], true);
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+use crate::mcp::{Caller, RequestContext, Transport};
+
+/// Argument names whose values never reach the audit trail.
+const REDACTED_KEYS: &[&str] = &[
+    "password",
+    "cipassword",
+    "key",
+    "token_value",
+    "secret",
+    "sshkeys",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+/// The `[audit]` config section.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuditSettings {
+    /// Directory for the JSON Lines audit files. No file is written if unset.
+    pub dir: Option<String>,
+    /// Audit file name prefix (default: `audit.jsonl`).
+    pub filename: Option<String>,
+    /// Rotation strategy: daily, hourly or never (default: daily).
+    pub rotate: Option<String>,
+    /// Also send every record to the local syslog socket.
+    #[serde(default)]
+    pub syslog: bool,
+}
+
+impl AuditSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.dir.is_some() || self.syslog
+    }
+}
+
+/// What became of a tool call. Only `Success` means the tool ran; a
+/// confirmation request or a dry-run plan sends nothing to PVE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    ConfirmationRequired,
+    DryRun,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditRecord<'a> {
+    pub timestamp: String,
+    pub transport: Transport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caller: Option<&'a Caller>,
+    pub tool: &'a str,
+    pub arguments: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upid: Option<String>,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u128,
+}
+
+impl<'a> AuditRecord<'a> {
+    pub fn new(
+        ctx: &'a RequestContext,
+        tool: &'a str,
+        args: &Value,
+        result: &Result<(Value, Outcome)>,
+        duration: Duration,
+    ) -> Self {
+        let (upid, outcome, error) = match result {
+            Ok((v, outcome)) => (find_upid(v), *outcome, None),
+            Err(e) => (None, Outcome::Error, Some(format!("{:#}", e))),
+        };
+        Self {
+            timestamp: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            transport: ctx.transport,
+            session_id: ctx.session_id.as_deref(),
+            caller: ctx.caller.as_ref(),
+            tool,
+            arguments: redact(args),
+            upid,
+            outcome,
+            error,
+            duration_ms: duration.as_millis(),
+        }
+    }
+}
+
+/// Append-only audit trail of tool invocations.
+pub struct AuditLog {
+    file: Option<Mutex<RollingFileAppender>>,
+    #[cfg(unix)]
+    syslog: Option<std::os::unix::net::UnixDatagram>,
+}
+
+impl AuditLog {
+    pub fn new(settings: &AuditSettings) -> Result<Self> {
+        let file = match &settings.dir {
+            Some(dir) => {
+                let rotation = match settings.rotate.as_deref().map(str::to_lowercase).as_deref() {
+                    Some("hourly") => Rotation::HOURLY,
+                    Some("never") => Rotation::NEVER,
+                    _ => Rotation::DAILY,
+                };
+                let appender = RollingFileAppender::builder()
+                    .rotation(rotation)
+                    .filename_prefix(settings.filename.as_deref().unwrap_or("audit.jsonl"))
+                    .build(dir)
+                    .context("Failed to create audit log file appender")?;
+                Some(Mutex::new(appender))
+            }
+            None => None,
+        };
+
+        #[cfg(unix)]
+        let syslog = if settings.syslog {
+            let socket = std::os::unix::net::UnixDatagram::unbound()?;
+            socket
+                .connect("/dev/log")
+                .context("Failed to connect to syslog at /dev/log")?;
+            Some(socket)
+        } else {
+            None
+        };
+        #[cfg(not(unix))]
+        if settings.syslog {
+            warn!("Audit syslog output is only supported on Unix");
+        }
+
+        Ok(Self {
+            file,
+            #[cfg(unix)]
+            syslog,
+        })
+    }
+
+    /// Writes one record. Failures are logged but never fail the tool call.
+    pub fn record(&self, record: &AuditRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize audit record: {}", e);
+                return;
+            }
+        };
+
+        if let Some(file) = &self.file {
+            let mut file = file.lock().unwrap();
+            if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+                warn!("Failed to write audit record: {}", e);
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(socket) = &self.syslog {
+            // user.info, or user.warning for failed calls
+            let priority = if record.outcome == Outcome::Error {
+                12
+            } else {
+                14
+            };
+            let msg = format!("<{}>proxmox-mcp-rs: {}", priority, line);
+            if let Err(e) = socket.send(msg.as_bytes()) {
+                warn!("Failed to send audit record to syslog: {}", e);
+            }
+        }
+    }
+}
+
+/// Replaces the values of secret-bearing keys, at any depth.
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    if REDACTED_KEYS.contains(&k.to_lowercase().as_str()) {
+                        (k.clone(), Value::String(REDACTED.to_string()))
+                    } else {
+                        (k.clone(), redact(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Finds the first task UPID mentioned in a tool result.
+pub fn find_upid(result: &Value) -> Option<String> {
+    let text = result["content"]
+        .as_array()?
+        .iter()
+        .filter_map(|c| c["text"].as_str())
+        .find(|t| t.contains("UPID:"))?;
+    // Skip labels such as "UPID: UPID:pve1:..."
+    text.match_indices("UPID:").find_map(|(start, _)| {
+        let upid: String = text[start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '"' && *c != ',')
+            .collect();
+        (upid.len() > "UPID:".len()).then_some(upid)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact() {
+        let args = json!({
+            "node": "pve1",
+            "password": "hunter2",
+            "nested": { "CIPassword": "x", "keep": 1 },
+            "list": [{ "key": "abc" }]
+        });
+        assert_eq!(
+            redact(&args),
+            json!({
+                "node": "pve1",
+                "password": REDACTED,
+                "nested": { "CIPassword": REDACTED, "keep": 1 },
+                "list": [{ "key": REDACTED }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_upid() {
+        let res = json!({ "content": [{ "type": "text", "text": "Clone initiated. UPID: UPID:pve1:0001:0002:65A0:qmclone:100:root@pam:" }] });
+        assert_eq!(
+            find_upid(&res).as_deref(),
+            Some("UPID:pve1:0001:0002:65A0:qmclone:100:root@pam:")
+        );
+        assert!(find_upid(&json!({ "content": [{ "type": "text", "text": "ok" }] })).is_none());
+    }
+
+    #[test]
+    fn test_audit_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::new(&AuditSettings {
+            dir: Some(dir.path().to_str().unwrap().to_string()),
+            rotate: Some("never".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let ctx = RequestContext {
+            transport: Transport::Sse,
+            session_id: Some("abc".to_string()),
+            caller: None,
+            notifier: None,
+            session_notifier: None,
+        };
+        let args = json!({ "node": "pve1", "vmid": 100, "password": "secret" });
+        let result = Ok((
+            json!({ "content": [{ "type": "text", "text": "UPID:pve1:1:2:3:qmstart:100:root@pam:" }] }),
+            Outcome::Success,
+        ));
+        log.record(&AuditRecord::new(
+            &ctx,
+            "start_vm",
+            &args,
+            &result,
+            Duration::from_millis(42),
+        ));
+        log.record(&AuditRecord::new(
+            &ctx,
+            "stop_vm",
+            &args,
+            &Err(anyhow::anyhow!("boom")),
+            Duration::from_millis(1),
+        ));
+
+        for outcome in [Outcome::ConfirmationRequired, Outcome::DryRun] {
+            log.record(&AuditRecord::new(
+                &ctx,
+                "delete_vm",
+                &args,
+                &Ok((json!({ "content": [] }), outcome)),
+                Duration::from_millis(1),
+            ));
+        }
+
+        let content = std::fs::read_to_string(dir.path().join("audit.jsonl")).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["transport"], "sse");
+        assert_eq!(lines[0]["session_id"], "abc");
+        assert_eq!(lines[0]["tool"], "start_vm");
+        assert_eq!(lines[0]["arguments"]["password"], REDACTED);
+        assert_eq!(lines[0]["upid"], "UPID:pve1:1:2:3:qmstart:100:root@pam:");
+        assert_eq!(lines[0]["outcome"], "success");
+        assert_eq!(lines[0]["duration_ms"], 42);
+        assert_eq!(lines[1]["outcome"], "error");
+        assert_eq!(lines[1]["error"], "boom");
+        assert_eq!(lines[2]["outcome"], "confirmation_required");
+        assert_eq!(lines[3]["outcome"], "dry_run");
+    }
+}
@@ -0,0 +1,130 @@
+use dashmap::DashMap;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+use crate::proxmox::ProxmoxClient;
+
+/// Requests with an ID that are still being handled, by session and ID.
+pub type InFlightRequests = DashMap<(Option<String>, String), Arc<InFlightRequest>>;
+
+/// Keeps a request registered in the in-flight requests until dropped.
+pub struct Registration {
+    requests: Arc<InFlightRequests>,
+    key: (Option<String>, String),
+    pub request: Arc<InFlightRequest>,
+}
+
+impl Registration {
+    pub fn new(requests: &Arc<InFlightRequests>, session_id: Option<String>, id: &Value) -> Self {
+        let key = (session_id, id.to_string());
+        let request = Arc::new(InFlightRequest::default());
+        requests.insert(key.clone(), request.clone());
+        Self {
+            requests: requests.clone(),
+            key,
+            request,
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        // A later request may have reused the ID
+        self.requests
+            .remove_if(&self.key, |_, r| Arc::ptr_eq(r, &self.request));
+    }
+}
+
+/// The PVE task an in-flight request is waiting on.
+#[derive(Clone)]
+pub struct WatchedTask {
+    pub client: ProxmoxClient,
+    pub node: String,
+    pub upid: String,
+}
+
+/// Cancellation handle of a request that is still being handled.
+pub struct InFlightRequest {
+    // Set to the client's reason once the request is cancelled.
+    cancel: watch::Sender<Option<String>>,
+    task: Mutex<Option<WatchedTask>>,
+}
+
+impl Default for InFlightRequest {
+    fn default() -> Self {
+        Self {
+            cancel: watch::channel(None).0,
+            task: Mutex::new(None),
+        }
+    }
+}
+
+impl InFlightRequest {
+    pub fn cancel(&self, reason: Option<&str>) {
+        self.cancel
+            .send_replace(Some(reason.unwrap_or("cancelled by client").to_string()));
+    }
+
+    /// Resolves with the reason once the request is cancelled.
+    pub async fn cancelled(&self) -> String {
+        let mut rx = self.cancel.subscribe();
+        // The sender lives as long as self, so waiting cannot fail
+        let reason = match rx.wait_for(Option::is_some).await {
+            Ok(reason) => reason.clone(),
+            Err(_) => None,
+        };
+        reason.unwrap_or_default()
+    }
+
+    /// Records the task this request waits on, so a cancellation can stop it.
+    pub fn watch_task(&self, client: &ProxmoxClient, node: &str, upid: &str) {
+        *self.task.lock().unwrap() = Some(WatchedTask {
+            client: client.clone(),
+            node: node.to_string(),
+            upid: upid.to_string(),
+        });
+    }
+
+    pub fn task(&self) -> Option<WatchedTask> {
+        self.task.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_wakes_waiter() {
+        let request = Arc::new(InFlightRequest::default());
+        let waiter = tokio::spawn({
+            let request = request.clone();
+            async move { request.cancelled().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        request.cancel(Some("user abort"));
+        assert_eq!(waiter.await.unwrap(), "user abort");
+
+        // Cancelling before anyone waits is not lost
+        let request = InFlightRequest::default();
+        request.cancel(None);
+        assert_eq!(request.cancelled().await, "cancelled by client");
+    }
+
+    #[test]
+    fn test_registration() {
+        let requests = Arc::new(InFlightRequests::new());
+        let id = Value::from(1);
+        let first = Registration::new(&requests, None, &id);
+        assert!(requests.contains_key(&(None, "1".to_string())));
+
+        // Dropping a stale registration leaves the one reusing its ID alone
+        let second = Registration::new(&requests, None, &id);
+        drop(first);
+        assert!(requests.contains_key(&(None, "1".to_string())));
+        drop(second);
+        assert!(requests.is_empty());
+    }
+}
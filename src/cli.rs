@@ -54,6 +54,14 @@ pub struct Args {
     )]
     pub no_verify_ssl: bool,
 
+    /// Additional API hosts of the same cluster to fail over to (comma separated)
+    #[arg(long, env = "PROXMOX_FAILOVER_HOSTS", value_delimiter = ',')]
+    pub failover_hosts: Vec<String>,
+
+    /// Discover failover hosts from the node addresses in cluster/status
+    #[arg(long, env = "PROXMOX_DISCOVER_NODES", default_value_t = false)]
+    pub discover_nodes: bool,
+
     /// Log level (error, warn, info, debug, trace)
     #[arg(short = 'L', long, env = "PROXMOX_LOG_LEVEL", default_value = "info")]
     pub log_level: String,
@@ -78,6 +86,14 @@ pub struct Args {
     #[arg(long, env = "PROXMOX_LOG_ROTATE", default_value = "daily")]
     pub log_rotate: String,
 
+    /// Directory for the JSON Lines audit log of tool invocations (disabled if unset)
+    #[arg(long, env = "PROXMOX_AUDIT_DIR")]
+    pub audit_dir: Option<String>,
+
+    /// Send audit records to the local syslog (/dev/log)
+    #[arg(long, env = "PROXMOX_AUDIT_SYSLOG", default_value_t = false)]
+    pub audit_syslog: bool,
+
     /// Server type (stdio or http)
     #[arg(short = 't', long, env = "PROXMOX_SERVER_TYPE")]
     pub server_type: Option<String>,
@@ -97,6 +113,34 @@ pub struct Args {
     /// Enable Lazy Loading mode (starts with minimal tools)
     #[arg(long, env = "PROXMOX_LAZY_MODE", default_value_t = false)]
     pub lazy_mode: bool,
+
+    /// Read-only mode: hide and refuse every tool that modifies the cluster
+    #[arg(long, env = "PROXMOX_READ_ONLY", default_value_t = false)]
+    pub read_only: bool,
+
+    /// Dry-run mode: mutating tools return the API requests they would send instead of sending them
+    #[arg(long, env = "PROXMOX_DRY_RUN", default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Require a confirmation token (from a preview call) before destructive tools act
+    #[arg(long, env = "PROXMOX_CONFIRM_DESTRUCTIVE", default_value_t = false)]
+    pub confirm_destructive: bool,
+
+    /// Stop the PVE task a cancelled request was waiting on (wait_for_task, progress-tracked tools)
+    #[arg(long, env = "PROXMOX_STOP_TASK_ON_CANCEL", default_value_t = false)]
+    pub stop_task_on_cancel: bool,
+
+    /// Seconds between cluster/resources polls while resources are subscribed to (default: 10)
+    #[arg(long, env = "PROXMOX_SUBSCRIPTION_POLL_SECS")]
+    pub subscription_poll_secs: Option<u64>,
+
+    /// Directory of additional prompt files (TOML, YAML or JSON), one prompt per file
+    #[arg(long, env = "PROXMOX_PROMPTS_DIR")]
+    pub prompts_dir: Option<String>,
+
+    /// Cluster used when a tool call does not name one (see [clusters.<name>] in the config file)
+    #[arg(long, env = "PROXMOX_DEFAULT_CLUSTER")]
+    pub default_cluster: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long fetched candidates are reused, so that a client completing as
+/// the user types does not query PVE on every keystroke.
+pub const CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// `completion/complete` returns at most this many values.
+const MAX_VALUES: usize = 100;
+
+/// A possible argument value. A guest can also be found by its name.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub value: String,
+    pub name: Option<String>,
+}
+
+impl Candidate {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            name: None,
+        }
+    }
+
+    /// Whether the value or the name starts with `prefix`, ignoring case.
+    fn matches(&self, prefix: &str) -> bool {
+        let prefix = prefix.to_lowercase();
+        self.value.to_lowercase().starts_with(&prefix)
+            || self
+                .name
+                .as_ref()
+                .is_some_and(|n| n.to_lowercase().starts_with(&prefix))
+    }
+}
+
+/// Candidates per cluster, argument and context (e.g. the node whose
+/// storages were listed).
+pub struct CompletionCache {
+    ttl: Duration,
+    entries: DashMap<String, (Instant, Vec<Candidate>)>,
+}
+
+impl CompletionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Returns the cached candidates for `key`, or fetches and caches them.
+    pub async fn get_or_fetch<F>(&self, key: String, fetch: F) -> Result<Vec<Candidate>>
+    where
+        F: Future<Output = Result<Vec<Candidate>>>,
+    {
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.0.elapsed() < self.ttl {
+                return Ok(entry.1.clone());
+            }
+        }
+        let candidates = fetch.await?;
+        self.entries
+            .insert(key, (Instant::now(), candidates.clone()));
+        Ok(candidates)
+    }
+}
+
+/// The `completion` object for the candidates matching `prefix`, in the
+/// order they were fetched.
+pub fn complete(candidates: &[Candidate], prefix: &str) -> Value {
+    // Shared storages and bridges show up once per node
+    let mut seen = HashSet::new();
+    let mut values: Vec<&str> = candidates
+        .iter()
+        .filter(|c| c.matches(prefix) && seen.insert(c.value.as_str()))
+        .map(|c| c.value.as_str())
+        .collect();
+    let total = values.len();
+    values.truncate(MAX_VALUES);
+    json!({
+        "values": values,
+        "total": total,
+        "hasMore": total > MAX_VALUES
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete() {
+        let candidates = vec![
+            Candidate {
+                value: "100".into(),
+                name: Some("web".into()),
+            },
+            Candidate {
+                value: "101".into(),
+                name: Some("db".into()),
+            },
+            Candidate::new("200"),
+            Candidate::new("100"),
+        ];
+        assert_eq!(complete(&candidates, "10")["values"], json!(["100", "101"]));
+        assert_eq!(complete(&candidates, "WE")["values"], json!(["100"]));
+        assert_eq!(complete(&candidates, "")["total"], 3);
+
+        let many: Vec<Candidate> = (0..150).map(|i| Candidate::new(i.to_string())).collect();
+        let completion = complete(&many, "");
+        assert_eq!(completion["values"].as_array().unwrap().len(), 100);
+        assert_eq!(completion["total"], 150);
+        assert_eq!(completion["hasMore"], true);
+    }
+}
@@ -0,0 +1,137 @@
+use dashmap::DashMap;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Tools that only act on the second call when confirmation is enabled.
+pub const CONFIRMED_TOOLS: &[&str] = &[
+    "delete_vm",
+    "delete_container",
+    "reset_vm",
+    "reset_container",
+    "rollback_vm",
+    "delete_snapshot",
+    "remove_disk",
+    "delete_storage",
+    "delete_pool",
+    "delete_user",
+    "delete_role",
+    "delete_replication_job",
+    "remove_ha_resource",
+];
+
+/// Argument carrying the token issued by the preview call.
+pub const CONFIRM_ARG: &str = "confirmation_token";
+
+pub const DEFAULT_CONFIRM_TTL: Duration = Duration::from_secs(300);
+
+struct PendingAction {
+    tool: String,
+    cluster: String,
+    args: Value,
+    expires_at: Instant,
+}
+
+/// Single-use confirmation tokens, each bound to one tool call.
+pub struct ConfirmationStore {
+    ttl: Duration,
+    pending: DashMap<String, PendingAction>,
+}
+
+impl ConfirmationStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: DashMap::new(),
+        }
+    }
+
+    pub fn requires_confirmation(tool: &str) -> bool {
+        CONFIRMED_TOOLS.contains(&tool)
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Issues a token that confirms exactly this call.
+    pub fn issue(&self, tool: &str, cluster: &str, args: &Value) -> String {
+        let now = Instant::now();
+        self.pending.retain(|_, p| p.expires_at > now);
+
+        let token = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+        self.pending.insert(
+            token.clone(),
+            PendingAction {
+                tool: tool.to_string(),
+                cluster: cluster.to_string(),
+                args: args.clone(),
+                expires_at: now + self.ttl,
+            },
+        );
+        token
+    }
+
+    /// Consumes a token. It is spent even if the call does not match, so a
+    /// token can never be retried against different arguments.
+    pub fn redeem(
+        &self,
+        token: &str,
+        tool: &str,
+        cluster: &str,
+        args: &Value,
+    ) -> Result<(), String> {
+        let Some((_, pending)) = self.pending.remove(token) else {
+            return Err("Unknown or already used confirmation token".to_string());
+        };
+        if pending.expires_at <= Instant::now() {
+            return Err(
+                "Confirmation token has expired; call the tool again for a new preview".to_string(),
+            );
+        }
+        if pending.tool != tool || pending.cluster != cluster || pending.args != *args {
+            return Err(format!(
+                "Confirmation token was issued for a different call ({} with other arguments)",
+                pending.tool
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_token_is_single_use_and_bound() {
+        let store = ConfirmationStore::new(DEFAULT_CONFIRM_TTL);
+        let args = json!({ "node": "pve1", "vmid": 100 });
+
+        let token = store.issue("delete_vm", "default", &args);
+        assert!(store.redeem(&token, "delete_vm", "default", &args).is_ok());
+        assert!(store.redeem(&token, "delete_vm", "default", &args).is_err());
+
+        let token = store.issue("delete_vm", "default", &args);
+        let other = json!({ "node": "pve1", "vmid": 101 });
+        assert!(store
+            .redeem(&token, "delete_vm", "default", &other)
+            .is_err());
+        // A mismatched attempt spends the token
+        assert!(store.redeem(&token, "delete_vm", "default", &args).is_err());
+
+        let token = store.issue("delete_vm", "default", &args);
+        assert!(store.redeem(&token, "delete_vm", "lab", &args).is_err());
+    }
+
+    #[test]
+    fn test_token_expires() {
+        let store = ConfirmationStore::new(Duration::ZERO);
+        let args = json!({ "storage": "nfs" });
+        let token = store.issue("delete_storage", "default", &args);
+        let err = store
+            .redeem(&token, "delete_storage", "default", &args)
+            .unwrap_err();
+        assert!(err.contains("expired"));
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Tools that destroy or roll back guest state and are therefore refused
+/// on guarded guests.
+pub const GUARDED_TOOLS: &[&str] = &[
+    "delete_vm",
+    "delete_container",
+    "reset_vm",
+    "reset_container",
+    "rollback_vm",
+    "delete_snapshot",
+    "remove_disk",
+    "remove_network",
+    "remove_vm_device",
+    "remove_lxc_mountpoint",
+];
+
+/// Argument that lets a single call act on a guarded guest, honored only
+/// when the operator enabled `allow_override`.
+pub const OVERRIDE_ARG: &str = "override_guard";
+
+/// Protection for critical guests from the `[guard]` config section.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GuestGuard {
+    /// Guests carrying any of these tags are guarded.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Also guard guests with PVE's `protection` flag set.
+    #[serde(default = "default_true")]
+    pub honor_protection: bool,
+    /// Whether `override_guard: true` may bypass the guard.
+    #[serde(default)]
+    pub allow_override: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for GuestGuard {
+    fn default() -> Self {
+        Self {
+            tags: Vec::new(),
+            honor_protection: true,
+            allow_override: false,
+        }
+    }
+}
+
+impl GuestGuard {
+    pub fn is_guarded_tool(tool: &str) -> bool {
+        GUARDED_TOOLS.contains(&tool)
+    }
+
+    /// Returns why the guest with this PVE config is guarded, if it is.
+    pub fn reason(&self, config: &Value) -> Option<String> {
+        if self.honor_protection && is_set(&config["protection"]) {
+            return Some("has the PVE protection flag set".to_string());
+        }
+        let tags = config["tags"].as_str().unwrap_or_default();
+        tags.split([';', ','])
+            .map(str::trim)
+            .find(|t| self.tags.iter().any(|g| g == t))
+            .map(|t| format!("is tagged '{}'", t))
+    }
+}
+
+/// PVE reports boolean config flags as `1`, but accept `true`/`"1"` too.
+fn is_set(v: &Value) -> bool {
+    match v {
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_i64() == Some(1),
+        Value::String(s) => s == "1",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_guard_reason() {
+        let guard = GuestGuard {
+            tags: vec!["protected".into(), "prod".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            guard.reason(&json!({ "tags": "web;prod" })).as_deref(),
+            Some("is tagged 'prod'")
+        );
+        assert!(guard.reason(&json!({ "protection": 1 })).is_some());
+        assert!(guard
+            .reason(&json!({ "tags": "web", "protection": 0 }))
+            .is_none());
+        assert!(guard.reason(&json!({})).is_none());
+
+        let tags_only = GuestGuard {
+            honor_protection: false,
+            ..guard
+        };
+        assert!(tags_only.reason(&json!({ "protection": 1 })).is_none());
+    }
+
+    #[test]
+    fn test_guarded_tools() {
+        assert!(GuestGuard::is_guarded_tool("delete_vm"));
+        assert!(GuestGuard::is_guarded_tool("remove_disk"));
+        assert!(!GuestGuard::is_guarded_tool("start_vm"));
+    }
+}
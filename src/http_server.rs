@@ -1,6 +1,6 @@
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::{
-    extract::{Query, Request, State},
+    extract::{ConnectInfo, Extension, Query, Request, State},
     middleware::{self, Next},
     response::{
         sse::{Event, Sse},
@@ -13,21 +13,247 @@ use dashmap::DashMap;
 use futures::stream::Stream;
 use log::{debug, error, info};
 use serde::Deserialize;
-use std::{collections::HashMap, convert::Infallible, sync::Arc, time::Duration};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, Weak};
+use std::time::{Duration, Instant};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use uuid::Uuid;
 
-use crate::mcp::{JsonRpcError, JsonRpcRequest, JsonRpcResponse, McpServer};
+use crate::mcp::{
+    is_cancelled, json_rpc_response, notification_forwarder, parse_request, Caller, McpServer,
+    Notifier, RequestContext, Transport, SUPPORTED_PROTOCOL_VERSIONS,
+};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Events kept per stream for `Last-Event-ID` replay.
+const EVENT_HISTORY: usize = 256;
+
+/// Finished response streams kept per session for `Last-Event-ID` replay.
+const STREAM_HISTORY: usize = 16;
+
+/// Streamable HTTP sessions without requests or open streams for this long
+/// are ended.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+type EventSender = mpsc::Sender<Result<Event, Infallible>>;
 
 #[derive(Clone)]
 struct AppState {
     mcp_server: McpServer,
-    sessions: Arc<DashMap<String, mpsc::Sender<Result<Event, Infallible>>>>,
+    sessions: Arc<DashMap<String, SseSession>>,
+    streams: Arc<DashMap<String, Arc<StreamSession>>>,
     auth_token: Option<String>,
 }
 
+/// A legacy HTTP+SSE session.
+#[derive(Clone)]
+struct SseSession {
+    tx: EventSender,
+    // Session-wide notifications, forwarded to the event stream
+    notifier: Notifier,
+}
+
+/// One SSE stream of a Streamable HTTP session: the standalone GET stream
+/// or the stream answering one POST. Its events are numbered `<stream>-<n>`
+/// and the latest are kept, so a client that lost the stream can resume
+/// it with `Last-Event-ID`.
+struct EventStream {
+    id: u64,
+    next_event_id: AtomicU64,
+    state: Mutex<EventStreamState>,
+}
+
+struct EventStreamState {
+    history: VecDeque<(u64, String)>,
+    // The connection the stream is delivered on, if one is open
+    sender: Option<EventSender>,
+    // Set once a response stream has sent its response
+    closed: bool,
+}
+
+impl EventStream {
+    fn new(id: u64, sender: Option<EventSender>) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            next_event_id: AtomicU64::new(1),
+            state: Mutex::new(EventStreamState {
+                history: VecDeque::new(),
+                sender,
+                closed: false,
+            }),
+        })
+    }
+
+    fn event(&self, event_id: u64, data: &str) -> Event {
+        Event::default()
+            .event("message")
+            .id(format!("{}-{}", self.id, event_id))
+            .data(data)
+    }
+
+    /// Records a message and delivers it on the open connection, if any.
+    async fn send(&self, data: String) {
+        let (event, sender) = {
+            let mut state = self.state.lock().unwrap();
+            let event_id = self.next_event_id.fetch_add(1, Ordering::SeqCst);
+            if state.history.len() == EVENT_HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back((event_id, data.clone()));
+            (self.event(event_id, &data), state.sender.clone())
+        };
+        if let Some(tx) = sender {
+            let _ = tx.send(Ok(event)).await;
+        }
+    }
+
+    /// Ends a response stream; its connection closes once drained.
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.sender = None;
+    }
+
+    fn is_connected(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.sender.as_ref().is_some_and(|tx| !tx.is_closed())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// Continues the stream on a new connection, first replaying the events
+    /// after `last_event_id`. Returns the receiving end of the connection.
+    fn resume(&self, last_event_id: u64) -> mpsc::Receiver<Result<Event, Infallible>> {
+        let (tx, rx) = mpsc::channel(EVENT_HISTORY + 100);
+        let mut state = self.state.lock().unwrap();
+        for (event_id, data) in state.history.iter().filter(|(id, _)| *id > last_event_id) {
+            // The channel has room for the whole history
+            let _ = tx.try_send(Ok(self.event(*event_id, data)));
+        }
+        if !state.closed {
+            state.sender = Some(tx);
+        }
+        rx
+    }
+}
+
+/// A Streamable HTTP session: the standalone stream for server-initiated
+/// messages and the recent response streams, each with its own event IDs.
+struct StreamSession {
+    protocol_version: String,
+    standalone: Arc<EventStream>,
+    responses: Mutex<VecDeque<Arc<EventStream>>>,
+    next_stream_id: AtomicU64,
+    last_active: Mutex<Instant>,
+    // Requests being handled, including those answered with plain JSON
+    in_flight: AtomicUsize,
+    // Session-wide notifications, delivered through `notify`
+    notifier: Notifier,
+}
+
+impl StreamSession {
+    fn new(protocol_version: &str) -> Arc<Self> {
+        Arc::new_cyclic(|session: &Weak<Self>| {
+            let session = session.clone();
+            let (notifier, _) = notification_forwarder(move |data| {
+                let session = session.upgrade();
+                async move {
+                    match session {
+                        Some(session) => {
+                            session.notify(data).await;
+                            true
+                        }
+                        None => false,
+                    }
+                }
+            });
+            Self {
+                protocol_version: protocol_version.to_string(),
+                standalone: EventStream::new(0, None),
+                responses: Mutex::new(VecDeque::new()),
+                next_stream_id: AtomicU64::new(1),
+                last_active: Mutex::new(Instant::now()),
+                in_flight: AtomicUsize::new(0),
+                notifier,
+            }
+        })
+    }
+
+    /// Opens a stream for the response to a POST.
+    fn open_stream(&self) -> (Arc<EventStream>, mpsc::Receiver<Result<Event, Infallible>>) {
+        let (tx, rx) = mpsc::channel(100);
+        let id = self.next_stream_id.fetch_add(1, Ordering::SeqCst);
+        let stream = EventStream::new(id, Some(tx));
+        let mut responses = self.responses.lock().unwrap();
+        // Forget the oldest finished stream; running ones are always kept
+        if responses.len() >= STREAM_HISTORY {
+            if let Some(i) = responses
+                .iter()
+                .position(|s| s.state.lock().unwrap().closed)
+            {
+                responses.remove(i);
+            }
+        }
+        responses.push_back(stream.clone());
+        (stream, rx)
+    }
+
+    /// The stream a `Last-Event-ID` of the form `<stream>-<n>` refers to,
+    /// and `n`.
+    fn find_event(&self, last_event_id: &str) -> Option<(Arc<EventStream>, u64)> {
+        let (stream, event) = last_event_id.split_once('-')?;
+        let (stream, event): (u64, u64) = (stream.parse().ok()?, event.parse().ok()?);
+        if stream == 0 {
+            return Some((self.standalone.clone(), event));
+        }
+        let responses = self.responses.lock().unwrap();
+        let found = responses.iter().find(|s| s.id == stream)?;
+        Some((found.clone(), event))
+    }
+
+    /// Delivers a server-initiated message on the GET stream, or keeps it
+    /// in the history until the client opens one.
+    async fn notify(&self, data: String) {
+        self.standalone.send(data).await;
+    }
+
+    fn touch(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+    }
+
+    /// Counts a request as running until the returned guard is dropped.
+    fn begin_request(self: &Arc<Self>) -> InFlightRequest {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightRequest(self.clone())
+    }
+
+    /// Whether the client has sent no request for `timeout`, has no stream
+    /// open and no request running.
+    fn is_idle(&self, timeout: Duration) -> bool {
+        self.last_active.lock().unwrap().elapsed() >= timeout
+            && self.in_flight.load(Ordering::SeqCst) == 0
+            && !self.standalone.is_connected()
+            && self.responses.lock().unwrap().iter().all(|s| s.is_closed())
+    }
+}
+
+/// Marks a request of a [`StreamSession`] as running while alive.
+struct InFlightRequest(Arc<StreamSession>);
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 #[derive(Deserialize)]
 struct MessageParams {
     session_id: String,
@@ -42,27 +268,330 @@ pub async fn run_http_server(
     let state = AppState {
         mcp_server,
         sessions: Arc::new(DashMap::new()),
+        streams: Arc::new(DashMap::new()),
         auth_token,
     };
 
-    let app = Router::new()
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                end_idle_sessions(&state, SESSION_IDLE_TIMEOUT);
+            }
+        }
+    });
+
+    let app = router(state);
+
+    let addr = format!("{}:{}", host, port);
+    info!("Starting HTTP MCP Server on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        // Streamable HTTP transport
+        .route(
+            "/mcp",
+            post(mcp_post_handler)
+                .get(mcp_get_handler)
+                .delete(mcp_delete_handler),
+        )
+        // Legacy HTTP+SSE transport
         .route("/sse", get(sse_handler))
         .route("/message", post(message_handler))
+        .route("/metrics", get(metrics_handler))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
         ))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state)
+}
 
-    let addr = format!("{}:{}", host, port);
-    info!("Starting HTTP MCP Server on {}", addr);
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        state.mcp_server.metrics(),
+    )
+}
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+/// The 400 reply to a body that is not a JSON-RPC request.
+fn invalid_message(resp: serde_json::Value) -> Response {
+    (StatusCode::BAD_REQUEST, Json(resp)).into_response()
+}
 
-    Ok(())
+fn list_changed_notification() -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/tools/list_changed"
+    })
+    .to_string()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    header_str(headers, "accept").is_some_and(|a| a.contains("text/event-stream"))
+}
+
+/// Looks up the session named by the `Mcp-Session-Id` header: 400 if the
+/// header is missing, 404 if the session is unknown or was terminated.
+fn stream_session(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(String, Arc<StreamSession>), (StatusCode, String)> {
+    let Some(session_id) = header_str(headers, SESSION_HEADER) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        ));
+    };
+    let Some(session) = state.streams.get(session_id).map(|s| s.clone()) else {
+        return Err((StatusCode::NOT_FOUND, "Session not found".to_string()));
+    };
+    if let Some(version) = header_str(headers, PROTOCOL_VERSION_HEADER) {
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported MCP-Protocol-Version: {}", version),
+            ));
+        }
+    }
+    session.touch();
+    Ok((session_id.to_string(), session))
+}
+
+async fn mcp_post_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    caller: Option<Extension<Caller>>,
+    body: String,
+) -> Response {
+    let req = match parse_request(&body) {
+        Ok(req) => req,
+        Err(resp) => return invalid_message(resp),
+    };
+    let caller = caller.map(|Extension(c)| c);
+
+    if req.method == "initialize" {
+        let session_id = Uuid::new_v4().to_string();
+        let id = req.id.clone().unwrap_or(serde_json::Value::Null);
+        let ctx = RequestContext {
+            transport: Transport::StreamableHttp,
+            session_id: Some(session_id.clone()),
+            caller,
+            notifier: None,
+            session_notifier: None,
+        };
+        let resp = state
+            .mcp_server
+            .handle_request_with_context(req, &ctx)
+            .await;
+        let version = resp.as_ref().ok().map(|result| {
+            result["protocolVersion"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        });
+        let mut response = Json(json_rpc_response(id, resp)).into_response();
+        // Only a successful initialize starts a session
+        if let Some(version) = version {
+            state
+                .streams
+                .insert(session_id.clone(), StreamSession::new(&version));
+            info!(
+                "New Streamable HTTP session {} (protocol {})",
+                session_id, version
+            );
+            if let Ok(value) = HeaderValue::from_str(&session_id) {
+                response.headers_mut().insert(SESSION_HEADER, value);
+            }
+        }
+        return response;
+    }
+
+    let (session_id, session) = match stream_session(&state, &headers) {
+        Ok(s) => s,
+        Err(resp) => return resp.into_response(),
+    };
+    let _in_flight = session.begin_request();
+    debug!(
+        "Received Streamable HTTP request for session {} ({}): {}",
+        session_id,
+        session.protocol_version,
+        req.redacted()
+    );
+
+    let mut ctx = RequestContext {
+        transport: Transport::StreamableHttp,
+        session_id: Some(session_id),
+        caller,
+        notifier: None,
+        session_notifier: Some(session.notifier.clone()),
+    };
+    let mcp = state.mcp_server.clone();
+
+    // Notifications and responses are only acknowledged
+    let Some(id) = req.id.clone() else {
+        if let Err(e) = mcp.handle_request_with_context(req, &ctx).await {
+            debug!("Notification failed: {:#}", e);
+        }
+        return StatusCode::ACCEPTED.into_response();
+    };
+
+    if !accepts_event_stream(&headers) {
+        // Progress goes to the GET stream, as the response is plain JSON
+        let notify_session = session.clone();
+        let (notifier, forwarder) = notification_forwarder(move |data| {
+            let session = notify_session.clone();
+            async move {
+                session.notify(data).await;
+                true
+            }
+        });
+        ctx.notifier = Some(notifier);
+        let resp = mcp.handle_request_with_context(req, &ctx).await;
+        drop(ctx);
+        let _ = forwarder.await;
+        if mcp.check_notification() {
+            session.notify(list_changed_notification()).await;
+        }
+        return Json(json_rpc_response(id, resp)).into_response();
+    }
+
+    // Answer on an SSE stream that closes after the response
+    let (stream, rx) = session.open_stream();
+    let notify_stream = stream.clone();
+    let (notifier, forwarder) = notification_forwarder(move |data| {
+        let stream = notify_stream.clone();
+        async move {
+            stream.send(data).await;
+            true
+        }
+    });
+    ctx.notifier = Some(notifier);
+    let registration = mcp.register_request(&req, &ctx);
+    tokio::spawn(async move {
+        let resp = mcp.handle_registered_request(req, &ctx, registration).await;
+        drop(ctx);
+        let _ = forwarder.await;
+        // A cancelled request gets no response; the stream just closes
+        if !resp.as_ref().is_err_and(is_cancelled) {
+            if let Ok(data) = serde_json::to_string(&json_rpc_response(id, resp)) {
+                stream.send(data).await;
+            }
+            if mcp.check_notification() {
+                stream.send(list_changed_notification()).await;
+            }
+        }
+        stream.close();
+    });
+    Sse::new(ReceiverStream::new(rx)).into_response()
+}
+
+/// Opens the stream for server-initiated messages. With `Last-Event-ID`,
+/// the stream that event belongs to is resumed instead: the messages the
+/// client missed on it are replayed, and a response stream that has not
+/// finished continues on this connection.
+async fn mcp_get_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !accepts_event_stream(&headers) {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    let (session_id, session) = match stream_session(&state, &headers) {
+        Ok(s) => s,
+        Err(resp) => return resp.into_response(),
+    };
+
+    // Without a known Last-Event-ID, the standalone stream continues with
+    // new messages only
+    let (stream, last) = header_str(&headers, "last-event-id")
+        .and_then(|last| session.find_event(last))
+        .unwrap_or((session.standalone.clone(), u64::MAX));
+    if last != u64::MAX {
+        debug!(
+            "Resuming stream {} after event {} for session {}",
+            stream.id, last, session_id
+        );
+    }
+    let rx = stream.resume(last);
+
+    Sse::new(ReceiverStream::new(rx))
+        .keep_alive(axum::response::sse::KeepAlive::new().interval(Duration::from_secs(15)))
+        .into_response()
+}
+
+async fn mcp_delete_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let (session_id, _) = match stream_session(&state, &headers) {
+        Ok(s) => s,
+        Err(resp) => return resp.into_response(),
+    };
+    end_stream_session(&state, &session_id);
+    info!("Streamable HTTP session {} terminated", session_id);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+fn end_stream_session(state: &AppState, session_id: &str) {
+    state.streams.remove(session_id);
+    state.mcp_server.end_session(Some(session_id));
+}
+
+/// Ends the Streamable HTTP sessions of clients that went away without
+/// sending DELETE.
+fn end_idle_sessions(state: &AppState, timeout: Duration) {
+    let idle: Vec<String> = state
+        .streams
+        .iter()
+        .filter(|s| s.is_idle(timeout))
+        .map(|s| s.key().clone())
+        .collect();
+    for session_id in idle {
+        end_stream_session(state, &session_id);
+        info!("Streamable HTTP session {} expired", session_id);
+    }
+}
+
+/// An HTTP+SSE event stream that ends its session when the client
+/// disconnects.
+struct SseStream {
+    inner: ReceiverStream<Result<Event, Infallible>>,
+    state: AppState,
+    session_id: String,
+}
+
+impl Stream for SseStream {
+    type Item = Result<Event, Infallible>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+impl Drop for SseStream {
+    fn drop(&mut self) {
+        self.state.sessions.remove(&self.session_id);
+        self.state.mcp_server.end_session(Some(&self.session_id));
+        info!("SSE session disconnected: {}", self.session_id);
+    }
 }
 
 async fn sse_handler(
@@ -71,7 +600,22 @@ async fn sse_handler(
     let session_id = Uuid::new_v4().to_string();
     let (tx, rx) = mpsc::channel(100);
 
-    state.sessions.insert(session_id.clone(), tx.clone());
+    let notify_tx = tx.clone();
+    let (notifier, _) = notification_forwarder(move |data| {
+        let tx = notify_tx.clone();
+        async move {
+            tx.send(Ok(Event::default().event("message").data(data)))
+                .await
+                .is_ok()
+        }
+    });
+    state.sessions.insert(
+        session_id.clone(),
+        SseSession {
+            tx: tx.clone(),
+            notifier,
+        },
+    );
 
     info!("New SSE session connected: {}", session_id);
 
@@ -81,8 +625,11 @@ async fn sse_handler(
         .send(Ok(Event::default().event("endpoint").data(endpoint_url)))
         .await;
 
-    // Create a stream that removes the session on drop
-    let stream = ReceiverStream::new(rx);
+    let stream = SseStream {
+        inner: ReceiverStream::new(rx),
+        state,
+        session_id,
+    };
 
     Sse::new(stream)
         .keep_alive(axum::response::sse::KeepAlive::new().interval(Duration::from_secs(15)))
@@ -91,46 +638,54 @@ async fn sse_handler(
 async fn message_handler(
     State(state): State<AppState>,
     Query(params): Query<MessageParams>,
-    Json(req): Json<JsonRpcRequest>,
+    caller: Option<Extension<Caller>>,
+    body: String,
 ) -> impl IntoResponse {
     let session_id = params.session_id;
+    let req = match parse_request(&body) {
+        Ok(req) => req,
+        Err(resp) => return invalid_message(resp),
+    };
 
-    let tx = if let Some(sender) = state.sessions.get(&session_id) {
-        sender.clone()
+    let (tx, session_notifier) = if let Some(session) = state.sessions.get(&session_id) {
+        (session.tx.clone(), session.notifier.clone())
     } else {
         return (axum::http::StatusCode::NOT_FOUND, "Session not found").into_response();
     };
 
     let mcp = state.mcp_server.clone();
+    debug!(
+        "Received HTTP request for session {}: {}",
+        session_id,
+        req.redacted()
+    );
+
+    let notify_tx = tx.clone();
+    let (notifier, forwarder) = notification_forwarder(move |data| {
+        let tx = notify_tx.clone();
+        async move {
+            tx.send(Ok(Event::default().event("message").data(data)))
+                .await
+                .is_ok()
+        }
+    });
+    let ctx = RequestContext {
+        transport: Transport::Sse,
+        session_id: Some(session_id.clone()),
+        caller: caller.map(|Extension(c)| c),
+        notifier: Some(notifier),
+        session_notifier: Some(session_notifier),
+    };
+    let registration = mcp.register_request(&req, &ctx);
 
     tokio::spawn(async move {
         let req_id = req.id.clone();
-        debug!(
-            "Received HTTP request for session {}: {:?}",
-            session_id, req
-        );
+        let resp = mcp.handle_registered_request(req, &ctx, registration).await;
+        drop(ctx);
+        let _ = forwarder.await;
 
-        let resp = mcp.handle_request(req).await;
-
-        if let Some(id) = req_id {
-            let json_resp = match resp {
-                Ok(result) => JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: Some(id),
-                    result: Some(result),
-                    error: None,
-                },
-                Err(e) => JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: Some(id),
-                    result: None,
-                    error: Some(JsonRpcError {
-                        code: -32603,
-                        message: e.to_string(),
-                        data: None,
-                    }),
-                },
-            };
+        if let Some(id) = req_id.filter(|_| !resp.as_ref().is_err_and(is_cancelled)) {
+            let json_resp = json_rpc_response(id, resp);
 
             if let Ok(data) = serde_json::to_string(&json_resp) {
                 // Send response as 'message' event
@@ -144,20 +699,16 @@ async fn message_handler(
 
             // Check for notifications
             if mcp.check_notification() {
-                let notification = serde_json::json!({
-                    "jsonrpc": "2.0",
-                    "method": "notifications/tools/list_changed"
-                });
-                if let Ok(data) = serde_json::to_string(&notification) {
-                    if let Err(e) = tx
-                        .send(Ok(Event::default().event("message").data(data)))
-                        .await
-                    {
-                        error!(
-                            "Failed to send notification to session {}: {}",
-                            session_id, e
-                        );
-                    }
+                if let Err(e) = tx
+                    .send(Ok(Event::default()
+                        .event("message")
+                        .data(list_changed_notification())))
+                    .await
+                {
+                    error!(
+                        "Failed to send notification to session {}: {}",
+                        session_id, e
+                    );
                 }
             }
         }
@@ -169,36 +720,49 @@ async fn message_handler(
 
 async fn auth_middleware(
     State(state): State<AppState>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    if let Some(ref token) = state.auth_token {
-        // 1. Check Header
-        if let Some(auth_header) = req.headers().get("Authorization") {
-            if let Ok(auth_str) = auth_header.to_str() {
-                if auth_str == format!("Bearer {}", token) {
-                    return Ok(next.run(req).await);
-                }
+    let auth = match state.auth_token {
+        Some(ref token) => authenticate(&req, token).ok_or(StatusCode::UNAUTHORIZED)?,
+        None => "none",
+    };
+
+    // Remember who called for the audit log
+    let remote_addr = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.to_string());
+    req.extensions_mut().insert(Caller { remote_addr, auth });
+
+    Ok(next.run(req).await)
+}
+
+/// Returns how the request proved knowledge of the token, if it did.
+fn authenticate(req: &Request, token: &str) -> Option<&'static str> {
+    // 1. Check Header
+    if let Some(auth_header) = req.headers().get("Authorization") {
+        if let Ok(auth_str) = auth_header.to_str() {
+            if auth_str == format!("Bearer {}", token) {
+                return Some("bearer");
             }
         }
+    }
 
-        // 2. Check Query Param
-        if let Some(query) = req.uri().query() {
-            let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
-                .into_owned()
-                .collect();
+    // 2. Check Query Param
+    if let Some(query) = req.uri().query() {
+        let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
 
-            if let Some(t) = params.get("token") {
-                if t == token {
-                    return Ok(next.run(req).await);
-                }
+        if let Some(t) = params.get("token") {
+            if t == token {
+                return Some("query_token");
             }
         }
-
-        return Err(StatusCode::UNAUTHORIZED);
     }
 
-    Ok(next.run(req).await)
+    None
 }
 
 #[cfg(test)]
@@ -221,11 +785,16 @@ mod tests {
         let state = AppState {
             mcp_server,
             sessions: Arc::new(DashMap::new()),
+            streams: Arc::new(DashMap::new()),
             auth_token: token,
         };
 
         Router::new()
             .route("/test", get(|| async { "ok" }))
+            .route(
+                "/caller",
+                get(|Extension(caller): Extension<Caller>| async move { caller.auth }),
+            )
             .layer(middleware::from_fn_with_state(
                 state.clone(),
                 auth_middleware,
@@ -306,4 +875,340 @@ mod tests {
         let response = app.oneshot(req).await.unwrap();
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
+
+    fn create_mcp_state() -> AppState {
+        let client = crate::proxmox::ProxmoxClient::new("localhost", 8006, true).unwrap();
+        AppState {
+            mcp_server: McpServer::new(client, false),
+            sessions: Arc::new(DashMap::new()),
+            streams: Arc::new(DashMap::new()),
+            auth_token: None,
+        }
+    }
+
+    fn create_mcp_app() -> Router {
+        router(create_mcp_state())
+    }
+
+    fn mcp_post(session: Option<&str>, accept: &str, body: serde_json::Value) -> Request<Body> {
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/mcp")
+            .header("content-type", "application/json")
+            .header("accept", accept);
+        if let Some(id) = session {
+            req = req.header(SESSION_HEADER, id);
+        }
+        req.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    /// The next chunk of an open SSE stream, or `None` if nothing arrives
+    /// within 100ms.
+    async fn next_chunk(stream: &mut axum::body::BodyDataStream) -> Option<String> {
+        use futures::StreamExt;
+
+        let chunk = tokio::time::timeout(Duration::from_millis(100), stream.next())
+            .await
+            .ok()??;
+        Some(String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_session_lifecycle() {
+        let app = create_mcp_app();
+
+        // initialize creates a session and negotiates the protocol version
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                None,
+                "application/json, text/event-stream",
+                serde_json::json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": { "protocolVersion": "2025-03-26" }
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let session = response.headers()[SESSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["result"]["protocolVersion"], "2025-03-26");
+
+        // Requests need the session header
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                None,
+                "application/json",
+                serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                Some("unknown"),
+                "application/json",
+                serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Notifications are acknowledged
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                Some(&session),
+                "application/json",
+                serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // Plain JSON response
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                Some(&session),
+                "application/json",
+                serde_json::json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/list" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["result"]["tools"].as_array().unwrap().len() > 10);
+
+        // SSE response with event IDs
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                Some(&session),
+                "application/json, text/event-stream",
+                serde_json::json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["content-type"].to_str().unwrap(),
+            "text/event-stream"
+        );
+        let body = body_string(response).await;
+        assert!(body.contains("id: 1-1"));
+        assert!(body.contains("\"id\":4"));
+
+        // DELETE ends the session
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/mcp")
+                    .header(SESSION_HEADER, &session)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = app
+            .oneshot(mcp_post(
+                Some(&session),
+                "application/json",
+                serde_json::json!({ "jsonrpc": "2.0", "id": 5, "method": "ping" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_resume() {
+        let state = create_mcp_state();
+        let app = router(state.clone());
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                None,
+                "application/json",
+                serde_json::json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": { "protocolVersion": "1999-01-01" }
+                }),
+            ))
+            .await
+            .unwrap();
+        let session = response.headers()[SESSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        // Unknown versions get the newest supported one
+        assert_eq!(
+            body["result"]["protocolVersion"],
+            SUPPORTED_PROTOCOL_VERSIONS[0]
+        );
+
+        // Each response stream numbers its own events
+        for id in 2..4 {
+            let response = app
+                .clone()
+                .oneshot(mcp_post(
+                    Some(&session),
+                    "text/event-stream",
+                    serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": "ping" }),
+                ))
+                .await
+                .unwrap();
+            let body = body_string(response).await;
+            assert!(body.contains(&format!("id: {}-1", id - 1)));
+        }
+
+        // Server-initiated messages go to the standalone stream 0
+        let stream_session = state.streams.get(&session).unwrap().clone();
+        for n in 1..3 {
+            stream_session
+                .notify(
+                    serde_json::json!({ "jsonrpc": "2.0", "method": "n", "params": { "n": n } })
+                        .to_string(),
+                )
+                .await;
+        }
+
+        let resume = |last_event_id: &str| {
+            Request::builder()
+                .method("GET")
+                .uri("/mcp")
+                .header("accept", "text/event-stream")
+                .header(SESSION_HEADER, &session)
+                .header("last-event-id", last_event_id)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Resuming the standalone stream replays only what it missed there,
+        // never responses that went out on response streams
+        let response = app.clone().oneshot(resume("0-1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut stream = response.into_body().into_data_stream();
+        let text = next_chunk(&mut stream).await.unwrap();
+        assert!(text.contains("id: 0-2"));
+        assert!(text.contains("\"n\":2"));
+        assert!(next_chunk(&mut stream).await.is_none());
+
+        // Resuming a finished response stream replays its response and ends
+        let response = app.oneshot(resume("2-0")).await.unwrap();
+        let body = body_string(response).await;
+        assert!(body.contains("id: 2-1"));
+        assert!(body.contains("\"id\":3"));
+        assert!(!body.contains("\"id\":2"));
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_idle_sessions() {
+        let state = create_mcp_state();
+        let app = router(state.clone());
+        let response = app
+            .clone()
+            .oneshot(mcp_post(
+                None,
+                "application/json",
+                serde_json::json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": { "protocolVersion": "2025-03-26" }
+                }),
+            ))
+            .await
+            .unwrap();
+        let session = response.headers()[SESSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        end_idle_sessions(&state, Duration::from_secs(60));
+        assert!(state.streams.contains_key(&session));
+
+        // So does a request still being handled
+        let in_flight = state.streams.get(&session).unwrap().begin_request();
+        end_idle_sessions(&state, Duration::ZERO);
+        assert!(state.streams.contains_key(&session));
+        drop(in_flight);
+
+        // An open GET stream keeps the session alive
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/mcp")
+                    .header("accept", "text/event-stream")
+                    .header(SESSION_HEADER, &session)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        end_idle_sessions(&state, Duration::ZERO);
+        assert!(state.streams.contains_key(&session));
+
+        drop(response);
+        end_idle_sessions(&state, Duration::ZERO);
+        assert!(!state.streams.contains_key(&session));
+        let response = app
+            .oneshot(mcp_post(
+                Some(&session),
+                "application/json",
+                serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sse_session_ends_on_disconnect() {
+        let state = create_mcp_state();
+        let app = router(state.clone());
+        let response = app
+            .oneshot(Request::builder().uri("/sse").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let mut stream = response.into_body().into_data_stream();
+        let endpoint = next_chunk(&mut stream).await.unwrap();
+        assert!(endpoint.contains("/message?session_id="));
+        assert_eq!(state.sessions.len(), 1);
+
+        drop(stream);
+        assert!(state.sessions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_auth_records_caller() {
+        let app = create_test_app(Some("secret".to_string()));
+
+        let req = Request::builder()
+            .uri("/caller?token=secret")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"query_token");
+    }
 }
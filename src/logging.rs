@@ -0,0 +1,281 @@
+use dashmap::DashMap;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::mcp::Notifier;
+
+/// MCP log levels (the syslog severities), least severe first.
+pub const LOG_LEVELS: &[&str] = &[
+    "debug",
+    "info",
+    "notice",
+    "warning",
+    "error",
+    "critical",
+    "alert",
+    "emergency",
+];
+
+/// Position of an MCP log level in `LOG_LEVELS`.
+pub fn parse_level(level: &str) -> Option<usize> {
+    LOG_LEVELS.iter().position(|l| *l == level)
+}
+
+fn severity(level: &Level) -> usize {
+    match *level {
+        Level::ERROR => 4,
+        Level::WARN => 3,
+        Level::INFO => 1,
+        _ => 0,
+    }
+}
+
+/// Sessions that asked for log messages with `logging/setLevel`, each with
+/// the lowest level it wants and the notifier that reaches it.
+#[derive(Default)]
+pub struct LogSessions {
+    sessions: DashMap<Option<String>, (usize, Notifier)>,
+}
+
+impl LogSessions {
+    pub fn set_level(&self, session: Option<String>, level: usize, notifier: Notifier) {
+        self.sessions.insert(session, (level, notifier));
+    }
+
+    pub fn end_session(&self, session: Option<&str>) {
+        self.sessions.retain(|s, _| s.as_deref() != session);
+    }
+
+    /// Sends `notifications/message` to `session` if its level admits
+    /// `severity`. A session that is gone is dropped.
+    fn publish(&self, session: &Option<String>, severity: usize, logger: &str, data: &Value) {
+        self.sessions.remove_if(session, |_, (level, notifier)| {
+            severity >= *level
+                && notifier
+                    .send(json!({
+                        "jsonrpc": "2.0",
+                        "method": "notifications/message",
+                        "params": {
+                            "level": LOG_LEVELS[severity],
+                            "logger": logger,
+                            "data": data
+                        }
+                    }))
+                    .is_err()
+        });
+    }
+}
+
+/// The session of a `request` span; `None` on stdio.
+struct RequestSession(Option<String>);
+
+/// Fields of a span, attached to every event logged inside it.
+struct SpanFields(Map<String, Value>);
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+/// Forwards this crate's log events, including those of the `log` macros,
+/// to the sessions in `LogSessions`. An event only reaches the session of
+/// the `request` span it happened in; events outside of any request, such
+/// as the transports' dumps of incoming messages, reach no session. The
+/// message and fields of an event become the notification's `data`,
+/// together with the fields of the spans it happened in (e.g. `tool: {
+/// name, cluster }`).
+pub struct McpLogLayer {
+    sessions: Arc<LogSessions>,
+}
+
+impl McpLogLayer {
+    pub fn new(sessions: Arc<LogSessions>) -> Self {
+        Self { sessions }
+    }
+
+    /// Events this layer needs to see; independent of the stderr log level.
+    pub fn filter() -> Targets {
+        Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::DEBUG)
+    }
+}
+
+impl<S> Layer<S> for McpLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = JsonVisitor::default();
+        attrs.record(&mut fields);
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if span.name() == "request" {
+            let session = fields.0.get("session").and_then(|s| s.as_str());
+            span.extensions_mut()
+                .insert(RequestSession(session.map(String::from)));
+        } else {
+            span.extensions_mut().insert(SpanFields(fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if self.sessions.sessions.is_empty() {
+            return;
+        }
+        let Some(session) = ctx.event_scope(event).and_then(|scope| {
+            scope
+                .into_iter()
+                .find_map(|s| s.extensions().get::<RequestSession>().map(|r| r.0.clone()))
+        }) else {
+            return;
+        };
+        let normalized = event.normalized_metadata();
+        let meta = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut fields = JsonVisitor::default();
+        event.record(&mut fields);
+        // Bridged `log` records carry their origin as `log.*` fields
+        let mut data: Map<String, Value> = fields
+            .0
+            .into_iter()
+            .filter(|(k, _)| !k.starts_with("log."))
+            .collect();
+        for span in ctx
+            .event_scope(event)
+            .into_iter()
+            .flat_map(|s| s.from_root())
+        {
+            if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                data.insert(span.name().to_string(), Value::Object(fields.clone()));
+            }
+        }
+        self.sessions.publish(
+            &session,
+            severity(meta.level()),
+            meta.target(),
+            &Value::Object(data),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_forwarding_by_level() {
+        let sessions = Arc::new(LogSessions::default());
+        let (debug_tx, mut debug_rx) = mpsc::unbounded_channel();
+        let (warn_tx, mut warn_rx) = mpsc::unbounded_channel();
+        sessions.set_level(Some("a".into()), parse_level("debug").unwrap(), debug_tx);
+        sessions.set_level(Some("b".into()), parse_level("warning").unwrap(), warn_tx);
+
+        let subscriber = tracing_subscriber::registry()
+            .with(McpLogLayer::new(sessions.clone()).with_filter(McpLogLayer::filter()));
+        tracing::subscriber::with_default(subscriber, || {
+            for session in ["a", "b"] {
+                let _request = tracing::info_span!("request", session).entered();
+                let _tool = tracing::info_span!("tool", name = "list_nodes").entered();
+                tracing::debug!(path = "nodes", "GET");
+                tracing::warn!("PVE endpoint down");
+            }
+        });
+
+        let first = debug_rx.try_recv().unwrap();
+        assert_eq!(first["method"], "notifications/message");
+        assert_eq!(first["params"]["level"], "debug");
+        assert_eq!(first["params"]["data"]["message"], "GET");
+        assert_eq!(first["params"]["data"]["path"], "nodes");
+        assert_eq!(first["params"]["data"]["tool"]["name"], "list_nodes");
+        assert!(first["params"]["data"].get("request").is_none());
+        assert_eq!(debug_rx.try_recv().unwrap()["params"]["level"], "warning");
+        assert!(debug_rx.try_recv().is_err());
+
+        let only = warn_rx.try_recv().unwrap();
+        assert_eq!(only["params"]["data"]["message"], "PVE endpoint down");
+        assert!(warn_rx.try_recv().is_err());
+
+        // A session that went away is dropped
+        drop(debug_rx);
+        tracing::subscriber::with_default(
+            tracing_subscriber::registry().with(McpLogLayer::new(sessions.clone())),
+            || {
+                let _request = tracing::info_span!("request", session = "a").entered();
+                tracing::error!("failed");
+            },
+        );
+        assert!(!sessions.sessions.contains_key(&Some("a".to_string())));
+    }
+
+    #[test]
+    fn test_forwarding_by_session() {
+        let sessions = Arc::new(LogSessions::default());
+        let (a_tx, mut a_rx) = mpsc::unbounded_channel();
+        let (b_tx, mut b_rx) = mpsc::unbounded_channel();
+        let (stdio_tx, mut stdio_rx) = mpsc::unbounded_channel();
+        sessions.set_level(Some("a".into()), 0, a_tx);
+        sessions.set_level(Some("b".into()), 0, b_tx);
+        sessions.set_level(None, 0, stdio_tx);
+
+        let subscriber = tracing_subscriber::registry().with(McpLogLayer::new(sessions));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("outside of any request");
+            {
+                let _request = tracing::info_span!("request", session = "a").entered();
+                tracing::debug!("for a");
+            }
+            let _request = tracing::info_span!("request", session = None::<&str>).entered();
+            tracing::debug!("for stdio");
+        });
+
+        assert_eq!(
+            a_rx.try_recv().unwrap()["params"]["data"]["message"],
+            "for a"
+        );
+        assert!(a_rx.try_recv().is_err());
+        assert!(b_rx.try_recv().is_err());
+        assert_eq!(
+            stdio_rx.try_recv().unwrap()["params"]["data"]["message"],
+            "for stdio"
+        );
+        assert!(stdio_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Some(0));
+        assert_eq!(parse_level("warning"), Some(3));
+        assert_eq!(parse_level("warn"), None);
+    }
+}
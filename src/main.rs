@@ -1,18 +1,34 @@
 #![allow(clippy::module_inception)]
 
+mod annotations;
+mod audit;
+mod cancel;
+mod completion;
+mod confirm;
+mod guard;
 mod http_server;
+mod logging;
 mod mcp;
+mod policy;
+mod progress;
+mod prompts;
 mod proxmox;
+mod scope;
 mod settings;
+mod subscriptions;
 mod tests;
+mod validation;
 
 use clap::Parser;
-use log::{error, info};
+use log::{error, info, warn};
 use mcp::McpServer;
 use proxmox::ProxmoxClient;
 use proxmox_mcp_rs::cli::Args;
 use settings::Settings;
+use std::collections::BTreeMap;
 use std::process;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
@@ -21,6 +37,7 @@ async fn main() {
     let args = Args::parse();
 
     // Initialize Logging
+    let log_sessions = Arc::new(logging::LogSessions::default());
     let _guard = {
         let filter_layer =
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&args.log_level));
@@ -55,7 +72,13 @@ async fn main() {
             None
         };
 
-        let registry = tracing_subscriber::registry().with(stdout_layer);
+        // Forwards log events to MCP sessions that asked for them
+        let mcp_layer = logging::McpLogLayer::new(log_sessions.clone())
+            .with_filter(logging::McpLogLayer::filter());
+
+        let registry = tracing_subscriber::registry()
+            .with(stdout_layer)
+            .with(mcp_layer);
 
         if let Some((layer, guard)) = file_layer {
             registry.with(layer).init();
@@ -74,6 +97,16 @@ async fn main() {
         }
     };
 
+    // Connection overrides only apply to the top-level cluster
+    let connection_overrides = args.host.is_some()
+        || args.port.is_some()
+        || args.user.is_some()
+        || args.password.is_some()
+        || args.token_name.is_some()
+        || args.token_value.is_some()
+        || !args.failover_hosts.is_empty()
+        || args.discover_nodes;
+
     // Override settings with CLI arguments if provided
     if let Some(host) = args.host {
         settings.host = Some(host);
@@ -96,6 +129,12 @@ async fn main() {
     if args.no_verify_ssl {
         settings.no_verify_ssl = Some(true);
     }
+    if !args.failover_hosts.is_empty() {
+        settings.failover_hosts = Some(args.failover_hosts);
+    }
+    if args.discover_nodes {
+        settings.discover_nodes = Some(true);
+    }
     if let Some(st) = args.server_type {
         settings.server_type = Some(st);
     }
@@ -111,6 +150,33 @@ async fn main() {
     if args.lazy_mode {
         settings.lazy_mode = Some(true);
     }
+    if args.read_only {
+        settings.read_only = Some(true);
+    }
+    if args.dry_run {
+        settings.dry_run = Some(true);
+    }
+    if let Some(dir) = args.audit_dir {
+        settings.audit.get_or_insert_with(Default::default).dir = Some(dir);
+    }
+    if args.audit_syslog {
+        settings.audit.get_or_insert_with(Default::default).syslog = true;
+    }
+    if args.confirm_destructive {
+        settings.confirm_destructive = Some(true);
+    }
+    if args.stop_task_on_cancel {
+        settings.stop_task_on_cancel = Some(true);
+    }
+    if let Some(secs) = args.subscription_poll_secs {
+        settings.subscription_poll_secs = Some(secs);
+    }
+    if let Some(dir) = args.prompts_dir {
+        settings.prompts_dir = Some(dir);
+    }
+    if let Some(cluster) = args.default_cluster {
+        settings.default_cluster = Some(cluster);
+    }
 
     // We don't override log settings in `settings` struct because we used them directly from CLI args
     // to initialize logging BEFORE loading other settings (so we can log config errors).
@@ -120,44 +186,166 @@ async fn main() {
         process::exit(1);
     }
 
-    // Safe to unwrap because validate() checks these
-    let host = settings.host.unwrap();
-    let port = settings.port.unwrap_or(8006);
-    let user = settings.user.unwrap();
-    let password = settings.password;
-    let token_name = settings.token_name;
-    let token_value = settings.token_value;
+    let multi_cluster = settings.clusters.as_ref().is_some_and(|c| !c.is_empty());
+    if multi_cluster && connection_overrides {
+        warn!(
+            "Ignoring --host, --port, --user, --password, --token-name, --token-value, \
+             --failover-hosts and --discover-nodes: clusters are configured in [clusters]"
+        );
+    }
+
+    let clusters = settings.cluster_settings();
+    let default_cluster = settings.default_cluster_name();
     let no_verify_ssl = settings.no_verify_ssl.unwrap_or(false);
     let server_type = settings.server_type.unwrap_or_else(|| "stdio".to_string());
     let http_host = settings.http_host.unwrap_or_else(|| "0.0.0.0".to_string());
     let http_port = settings.http_port.unwrap_or(3000);
     let http_auth_token = settings.http_auth_token;
     let lazy_mode = settings.lazy_mode.unwrap_or(false);
+    let read_only = settings.read_only.unwrap_or(false);
+    let dry_run = settings.dry_run.unwrap_or(false);
+    let confirm_destructive = settings.confirm_destructive.unwrap_or(false);
+    let stop_task_on_cancel = settings.stop_task_on_cancel.unwrap_or(false);
 
-    info!("Connecting to Proxmox at {}:{}", host, port);
+    let mut clients = BTreeMap::new();
+    for (name, cluster) in clusters {
+        // Safe to unwrap because validate() checks these
+        let host = cluster.host.unwrap();
+        let port = cluster.port.unwrap_or(8006);
+        let user = cluster.user.unwrap();
+        let verify_ssl = !cluster.no_verify_ssl.unwrap_or(no_verify_ssl);
 
-    let mut client = match ProxmoxClient::new(&host, port, !no_verify_ssl) {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Failed to create client: {}", e);
-            process::exit(1);
+        info!(
+            "Connecting to Proxmox cluster '{}' at {}:{}",
+            name, host, port
+        );
+
+        let mut client = match ProxmoxClient::new(&host, port, verify_ssl) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("Failed to create client for cluster '{}': {}", name, e);
+                process::exit(1);
+            }
+        };
+
+        if let Some(retry) = &settings.retry {
+            client.set_retry_policy(retry.clone());
+        }
+        if let Some(limit) = &settings.rate_limit {
+            client.set_rate_limit(limit.clone());
+        }
+        if let Some(cache) = &settings.cache {
+            client.set_cache(cache.clone());
         }
-    };
 
-    if let (Some(t_name), Some(t_value)) = (token_name, token_value) {
-        info!("Using API Token authentication");
-        client.set_api_token(&user, &t_name, &t_value);
-    } else if let Some(pass) = password {
-        if let Err(e) = client.login(&user, &pass).await {
-            error!("Authentication failed: {}", e);
+        if let (Some(t_name), Some(t_value)) = (cluster.token_name, cluster.token_value) {
+            info!("Using API Token authentication for cluster '{}'", name);
+            client.set_api_token(&user, &t_name, &t_value);
+        } else if let Some(pass) = cluster.password {
+            if let Err(e) = client.login(&user, &pass).await {
+                error!("Authentication failed for cluster '{}': {}", name, e);
+                // One unreachable cluster must not take the others down;
+                // its calls log in again or report the error.
+                if !multi_cluster {
+                    process::exit(1);
+                }
+                warn!("Cluster '{}' is unavailable until a login succeeds", name);
+            }
+        } else {
+            error!("No authentication method provided for cluster '{}'", name);
             process::exit(1);
         }
-    } else {
-        error!("No authentication method provided");
-        process::exit(1);
+
+        for failover_host in cluster.failover_hosts.unwrap_or_default() {
+            if let Err(e) = client.add_failover_host(&failover_host) {
+                error!("Invalid failover host '{}': {}", failover_host, e);
+                process::exit(1);
+            }
+        }
+        if cluster.discover_nodes.unwrap_or(false) {
+            match client.discover_endpoints().await {
+                Ok(n) => info!("Discovered {} failover host(s) for cluster '{}'", n, name),
+                Err(e) => warn!("Node discovery failed for cluster '{}': {}", name, e),
+            }
+        }
+
+        clients.insert(name, client);
     }
 
-    let mut server = McpServer::new(client, lazy_mode);
+    let mut server = match McpServer::with_clusters(clients, &default_cluster, lazy_mode) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Configuration error: {}", e);
+            process::exit(1);
+        }
+    };
+    server.set_log_sessions(log_sessions);
+    if read_only {
+        info!("Read-only mode enabled: tools that modify the cluster are disabled");
+        server.set_read_only(true);
+    }
+    if dry_run {
+        info!("Dry-run mode enabled: mutating tools only report the requests they would send");
+        server.set_dry_run(true);
+    }
+    if confirm_destructive {
+        let ttl = settings
+            .confirm_ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or(confirm::DEFAULT_CONFIRM_TTL);
+        info!(
+            "Destructive tools require confirmation (tokens valid for {}s)",
+            ttl.as_secs()
+        );
+        server.enable_confirmation(ttl);
+    }
+    if stop_task_on_cancel {
+        info!("Cancelling a request also stops the PVE task it was waiting on");
+        server.set_stop_task_on_cancel(true);
+    }
+    if let Some(secs) = settings.subscription_poll_secs {
+        server.set_subscription_poll_interval(Duration::from_secs(secs.max(1)));
+    }
+    if let Some(dir) = settings.prompts_dir {
+        let mut prompts = prompts::PromptLibrary::default();
+        match prompts.load_dir(std::path::Path::new(&dir)) {
+            Ok(n) => {
+                info!("Loaded {} prompt(s) from {}", n, dir);
+                server.set_prompts(prompts);
+            }
+            Err(e) => {
+                error!("Failed to load prompts: {:#}", e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(audit) = settings.audit.filter(|a| a.is_enabled()) {
+        match audit::AuditLog::new(&audit) {
+            Ok(log) => {
+                info!("Audit log enabled");
+                server.set_audit_log(log);
+            }
+            Err(e) => {
+                error!("Failed to set up audit log: {:#}", e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(policy) = settings.policy {
+        info!(
+            "Tool policy: allow {:?}, deny {:?}",
+            policy.allow, policy.deny
+        );
+        server.set_policy(policy);
+    }
+    if let Some(scope) = settings.scope {
+        info!("Resource scope: {:?}", scope);
+        server.set_scope(scope);
+    }
+    if let Some(guard) = settings.guard {
+        info!("Guest guard: tags {:?}", guard.tags);
+        server.set_guard(guard);
+    }
 
     match server_type.as_str() {
         "http" => {
@@ -1,10 +1,174 @@
-use crate::proxmox::ProxmoxClient;
+use crate::annotations::annotate;
+use crate::audit::{find_upid, AuditLog, AuditRecord, Outcome};
+use crate::cancel::{InFlightRequest, InFlightRequests, Registration, WatchedTask};
+use crate::completion::{complete, Candidate, CompletionCache};
+use crate::confirm::{ConfirmationStore, CONFIRM_ARG};
+use crate::guard::{GuestGuard, OVERRIDE_ARG};
+use crate::logging::{parse_level, LogSessions};
+use crate::policy::ToolPolicy;
+use crate::progress::ProgressReporter;
+use crate::prompts::{Prompt, PromptLibrary};
+use crate::proxmox::client::{NodeInfo, SnapshotInfo, StorageInfo, TaskInfo, TaskStatus, VmInfo};
+use crate::proxmox::error::describe_fields;
+use crate::proxmox::metrics;
+use crate::proxmox::{ProxmoxClient, ProxmoxError};
+use crate::scope::ResourceScope;
+use crate::subscriptions::{subscribable, Snapshot, Subscriptions, Target};
+use crate::validation::validate;
 use anyhow::Result;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::io::{self, BufRead, Write};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::io;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::Instrument;
+
+/// MCP protocol revisions this server speaks, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Answers an `initialize` request: the client's version if we support it,
+/// otherwise the newest one we do.
+pub fn negotiate_protocol_version(requested: Option<&str>) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| Some(**v) == requested)
+        .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0])
+}
+
+/// Fixed resources: URI, name and description.
+const RESOURCES: &[(&str, &str, &str)] = &[
+    (
+        "proxmox://vms",
+        "List of VMs",
+        "A live list of all VMs and Containers",
+    ),
+    (
+        "proxmox://cluster/ha",
+        "HA resources",
+        "HA-managed resources and HA groups",
+    ),
+];
+
+/// Parameterized resources: URI template, name and description.
+const RESOURCE_TEMPLATES: &[(&str, &str, &str)] = &[
+    (
+        "proxmox://node/{node}/status",
+        "Node status",
+        "CPU, memory, uptime and versions of a node",
+    ),
+    (
+        "proxmox://node/{node}/syslog",
+        "Node syslog",
+        "The latest syslog lines of a node",
+    ),
+    (
+        "proxmox://guest/{vmid}/config",
+        "Guest config",
+        "Current configuration of a VM or container",
+    ),
+    (
+        "proxmox://guest/{vmid}/snapshots",
+        "Guest snapshots",
+        "Snapshots of a VM or container",
+    ),
+    (
+        "proxmox://task/{node}/{upid}/log",
+        "Task log",
+        "Log output of a task",
+    ),
+    (
+        "proxmox://storage/{node}/{storage}/content",
+        "Storage content",
+        "Volumes, ISOs, templates and backups on a storage",
+    ),
+];
+
+/// Decodes `%XX` escapes in a resource URI segment, as clients may encode
+/// the `:` and `@` of a UPID.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// How a request reached the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    #[default]
+    Stdio,
+    Sse,
+    StreamableHttp,
+}
+
+/// The HTTP client that made a request, as seen by the auth middleware.
+#[derive(Debug, Clone, Serialize)]
+pub struct Caller {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_addr: Option<String>,
+    /// How the caller authenticated: `bearer`, `query_token` or `none`.
+    pub auth: &'static str,
+}
+
+/// Channel for JSON-RPC notifications a request emits while it runs.
+pub type Notifier = mpsc::UnboundedSender<Value>;
+
+/// Spawns a task that hands every notification sent on the returned
+/// `Notifier` to `sink`, which returns false once its destination is gone.
+/// The task ends once all senders are dropped, so awaiting it after the
+/// request completes flushes pending notifications ahead of the response.
+pub fn notification_forwarder<F, Fut>(sink: F) -> (Notifier, JoinHandle<()>)
+where
+    F: Fn(String) -> Fut + Send + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let handle = tokio::spawn(async move {
+        while let Some(notification) = rx.recv().await {
+            if !sink(notification.to_string()).await {
+                break;
+            }
+        }
+    });
+    (tx, handle)
+}
+
+/// Where a request came from, for auditing, and where its notifications go.
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub transport: Transport,
+    pub session_id: Option<String>,
+    pub caller: Option<Caller>,
+    /// Notifications about this request, such as progress.
+    pub notifier: Option<Notifier>,
+    /// Notifications for the session that outlive the request, such as
+    /// resource updates.
+    pub session_notifier: Option<Notifier>,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JsonRpcRequest {
@@ -14,6 +178,13 @@ pub struct JsonRpcRequest {
     pub id: Option<Value>,
 }
 
+impl JsonRpcRequest {
+    /// The request for logging, without the values of secret arguments.
+    pub fn redacted(&self) -> Value {
+        crate::audit::redact(&serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
@@ -32,28 +203,461 @@ pub struct JsonRpcError {
     pub data: Option<Value>,
 }
 
+/// The result returned instead of running a tool that awaits confirmation.
+fn confirmation_required(name: &str, preview: &Value, token: &str, ttl: Duration) -> Value {
+    let text = format!(
+        "Confirmation required: '{}' has NOT been executed.\n\n{}\n\nTo proceed, call '{}' again with the same arguments plus \"{}\": \"{}\" within {} seconds.",
+        name,
+        serde_json::to_string_pretty(preview).unwrap_or_default(),
+        name,
+        CONFIRM_ARG,
+        token,
+        ttl.as_secs()
+    );
+    json!({ "content": [{ "type": "text", "text": text }] })
+}
+
+/// A tool result carrying `value` as pretty-printed text and, under `key`,
+/// as `structuredContent` matching the tool's `outputSchema`.
+fn structured_result<T: Serialize>(key: &str, value: &T) -> Result<Value> {
+    let value = serde_json::to_value(value)?;
+    Ok(json!({
+        "content": [{ "type": "text", "text": serde_json::to_string_pretty(&value)? }],
+        "structuredContent": { key: value }
+    }))
+}
+
+/// The `outputSchema` of a tool whose `structuredContent` holds `schema`
+/// under `key`.
+fn output_schema(key: &str, schema: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": { key: schema },
+        "required": [key]
+    })
+}
+
+fn list_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// Accepts a VMID given either as a number or as a numeric string.
+fn parse_vmid(v: &Value) -> Option<i64> {
+    v.as_i64()
+        .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+/// The guest a tool call acts on, if any. Most tools take `vmid`; the reset
+/// tools, HA resources (`vm:100`) and replication jobs (`100-0`) name it
+/// differently.
+fn target_vmid(name: &str, args: &Value) -> Option<i64> {
+    if let Some(id) = ["vmid", "vm_id", "container_id"]
+        .iter()
+        .find_map(|key| args.get(*key).and_then(parse_vmid))
+    {
+        return Some(id);
+    }
+    if name.ends_with("ha_resource") {
+        let sid = args.get("sid")?.as_str()?;
+        return sid.rsplit(':').next()?.parse().ok();
+    }
+    if name.ends_with("replication_job") {
+        let id = args.get("id")?.as_str()?;
+        return id.split('-').next()?.parse().ok();
+    }
+    None
+}
+
+/// Builds the JSON-RPC reply to a request from the handler's result. Every
+/// transport answers through this.
+pub fn json_rpc_response(id: Value, resp: Result<Value>) -> JsonRpcResponse {
+    match resp {
+        Ok(result) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => error_response(id, json_rpc_error(&e)),
+    }
+}
+
+fn error_response(id: Value, error: JsonRpcError) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: Some(id),
+        result: None,
+        error: Some(error),
+    }
+}
+
+/// Parses one JSON-RPC message, or returns the response to send instead:
+/// -32700 for invalid JSON, -32600 for JSON that is not a request.
+pub fn parse_request(input: &str) -> std::result::Result<JsonRpcRequest, Value> {
+    let invalid = |id: Value, code: i64, message: String| {
+        json!(error_response(
+            id,
+            JsonRpcError {
+                code,
+                message,
+                data: None,
+            },
+        ))
+    };
+    let value: Value = serde_json::from_str(input)
+        .map_err(|e| invalid(Value::Null, -32700, format!("Parse error: {}", e)))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value)
+        .map_err(|e| invalid(id, -32600, format!("Invalid request: {}", e)))
+}
+
+/// Maps an error to its JSON-RPC code, message and structured `data`: the
+/// HTTP status, body and per-parameter `errors` of a rejected PVE request,
+/// the UPID of a failed task, whether retrying may help, and the cluster a
+/// tool call was routed to.
+pub fn json_rpc_error(e: &anyhow::Error) -> JsonRpcError {
+    let mut data = serde_json::Map::new();
+    let code = if let Some(mcp_err) = e.downcast_ref::<McpError>() {
+        if let McpError::InvalidArguments(errors) = mcp_err {
+            data.insert("errors".into(), json!(errors));
+        }
+        mcp_err.code()
+    } else if let Some(pve_err) = e.downcast_ref::<ProxmoxError>() {
+        data.insert("retryable".into(), pve_err.is_retryable().into());
+        match pve_err {
+            ProxmoxError::Auth(_) => -32001,
+            ProxmoxError::Timeout(_) => -32002,
+            ProxmoxError::NotFound(_) => -32004,
+            ProxmoxError::Task(upid) => {
+                data.insert("upid".into(), upid.clone().into());
+                -32603
+            }
+            ProxmoxError::Validation(errors) => {
+                data.insert("status".into(), 400.into());
+                data.insert("errors".into(), json!(errors));
+                -32602
+            }
+            ProxmoxError::Api(status, body) => {
+                data.insert("status".into(), status.as_u16().into());
+                data.insert("details".into(), body.clone().into());
+                match status.as_u16() {
+                    400 => -32602,
+                    401 | 403 => -32001,
+                    404 => -32004,
+                    _ => -32603,
+                }
+            }
+            _ => -32603,
+        }
+    } else {
+        -32603
+    };
+    let data = (!data.is_empty()).then_some(Value::Object(data));
+    let (message, data) = tag_cluster(e, format!("{:#}", e), data);
+    JsonRpcError {
+        code,
+        message,
+        data,
+    }
+}
+
+/// Whether a `tools/call` error is the tool's own failure, including
+/// arguments it cannot run with, rather than a refusal by the server.
+fn is_tool_failure(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<McpError>(),
+        None | Some(McpError::InvalidArguments(_))
+    )
+}
+
+/// The `tools/call` result for a tool that failed while running. MCP wants
+/// these reported as results with `isError` so the model can see and react
+/// to them; refusals by the server's own checks remain JSON-RPC errors.
+fn tool_error_result(e: &anyhow::Error) -> Value {
+    let error = json_rpc_error(e);
+    let mut content = vec![json!({ "type": "text", "text": error.message })];
+    if let Some(data) = &error.data {
+        content.push(json!({
+            "type": "text",
+            "text": serde_json::to_string_pretty(&json!({ "code": error.code, "data": data }))
+                .unwrap_or_default()
+        }));
+    }
+    json!({ "content": content, "isError": true })
+}
+
+/// Prefixes an error message with the cluster a failed tool call was
+/// routed to and records it in the error data.
+fn tag_cluster(e: &anyhow::Error, message: String, data: Option<Value>) -> (String, Option<Value>) {
+    let Some(cluster) = e.downcast_ref::<ClusterContext>() else {
+        return (message, data);
+    };
+    let message = if message.starts_with(&cluster.to_string()) {
+        message
+    } else {
+        format!("{}: {}", cluster, message)
+    };
+    let mut data = data.unwrap_or_else(|| json!({}));
+    data["cluster"] = json!(cluster.0);
+    (message, Some(data))
+}
+
 struct McpState {
     lazy_mode: bool,
     tools_loaded: bool,
     should_notify: bool,
 }
 
+/// Errors raised by the MCP layer itself rather than by the Proxmox API.
+#[derive(thiserror::Error, Debug)]
+pub enum McpError {
+    #[error("Tool '{0}' modifies the cluster and is disabled in read-only mode")]
+    ReadOnly(String),
+
+    #[error("Tool '{0}' is not permitted by the server's tool policy")]
+    PolicyDenied(String),
+
+    #[error("Out of scope: {0}")]
+    OutOfScope(String),
+
+    #[error("{0}")]
+    Confirmation(String),
+
+    #[error("Request cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Method not found: {0}")]
+    MethodNotFound(String),
+
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+
+    #[error("Invalid arguments: {}", describe_fields(.0))]
+    InvalidArguments(BTreeMap<String, String>),
+
+    #[error("Guest {vmid} {reason}; refusing to run '{tool}'{hint}")]
+    Guarded {
+        tool: String,
+        vmid: i64,
+        reason: String,
+        hint: &'static str,
+    },
+}
+
+impl McpError {
+    /// JSON-RPC error code reported to the client.
+    pub fn code(&self) -> i64 {
+        match self {
+            McpError::ReadOnly(_) => -32005,
+            McpError::PolicyDenied(_) => -32006,
+            McpError::OutOfScope(_) => -32007,
+            McpError::Guarded { .. } => -32008,
+            McpError::Confirmation(_) => -32009,
+            McpError::Cancelled(_) => -32800,
+            McpError::MethodNotFound(_) => -32601,
+            McpError::InvalidParams(_) | McpError::InvalidArguments(_) => -32602,
+        }
+    }
+}
+
+/// Whether a request failed because the client cancelled it. Such requests
+/// get no response.
+pub fn is_cancelled(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<McpError>(), Some(McpError::Cancelled(_)))
+}
+
+/// Attached to errors from tool calls so transports can report which
+/// cluster the call was routed to.
+#[derive(Debug)]
+pub struct ClusterContext(pub String);
+
+impl fmt::Display for ClusterContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cluster '{}'", self.0)
+    }
+}
+
+/// Named Proxmox clients served by one MCP server.
+struct ClusterRegistry {
+    clients: BTreeMap<String, ProxmoxClient>,
+    default: String,
+}
+
 #[derive(Clone)]
 pub struct McpServer {
     client: ProxmoxClient,
+    cluster: String,
+    clusters: Arc<ClusterRegistry>,
+    read_only: bool,
+    policy: Arc<ToolPolicy>,
+    scope: Arc<ResourceScope>,
+    guard: Option<Arc<GuestGuard>>,
+    confirmations: Option<Arc<ConfirmationStore>>,
+    dry_run: bool,
+    audit: Option<Arc<AuditLog>>,
+    stop_task_on_cancel: bool,
+    in_flight: Arc<InFlightRequests>,
+    tools_changed: Arc<Notify>,
+    subscriptions: Arc<Subscriptions>,
+    prompts: Arc<PromptLibrary>,
+    log_sessions: Arc<LogSessions>,
+    completions: Arc<CompletionCache>,
+    // Per tools/call: where to report progress and the request's cancellation handle
+    progress: Option<ProgressReporter>,
+    request: Option<Arc<InFlightRequest>>,
     state: Arc<Mutex<McpState>>,
 }
 
 impl McpServer {
+    #[cfg(test)]
     pub fn new(client: ProxmoxClient, lazy_mode: bool) -> Self {
-        Self {
+        let clients = BTreeMap::from([(crate::settings::DEFAULT_CLUSTER.to_string(), client)]);
+        Self::with_clusters(clients, crate::settings::DEFAULT_CLUSTER, lazy_mode)
+            .expect("default cluster is registered")
+    }
+
+    pub fn with_clusters(
+        clients: BTreeMap<String, ProxmoxClient>,
+        default_cluster: &str,
+        lazy_mode: bool,
+    ) -> Result<Self> {
+        let client = clients
+            .get(default_cluster)
+            .cloned()
+            .ok_or(anyhow::anyhow!(
+                "Default cluster '{}' is not configured",
+                default_cluster
+            ))?;
+        Ok(Self {
             client,
+            cluster: default_cluster.to_string(),
+            clusters: Arc::new(ClusterRegistry {
+                clients,
+                default: default_cluster.to_string(),
+            }),
+            read_only: false,
+            policy: Arc::new(ToolPolicy::default()),
+            scope: Arc::new(ResourceScope::default()),
+            guard: None,
+            confirmations: None,
+            dry_run: false,
+            audit: None,
+            stop_task_on_cancel: false,
+            in_flight: Arc::new(InFlightRequests::new()),
+            tools_changed: Arc::new(Notify::new()),
+            subscriptions: Arc::new(Subscriptions::new(
+                crate::subscriptions::DEFAULT_POLL_INTERVAL,
+            )),
+            prompts: Arc::new(PromptLibrary::default()),
+            log_sessions: Arc::new(LogSessions::default()),
+            completions: Arc::new(CompletionCache::new(crate::completion::CACHE_TTL)),
+            progress: None,
+            request: None,
             state: Arc::new(Mutex::new(McpState {
                 lazy_mode,
                 tools_loaded: !lazy_mode,
                 should_notify: false,
             })),
-        }
+        })
+    }
+
+    /// Hides and refuses every tool that is not annotated as read-only.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Restricts the tools that can be listed and called.
+    pub fn set_policy(&mut self, policy: ToolPolicy) {
+        self.policy = Arc::new(policy);
+    }
+
+    /// Restricts the guests and nodes that tools may act on.
+    pub fn set_scope(&mut self, scope: ResourceScope) {
+        self.scope = Arc::new(scope);
+    }
+
+    /// Refuses destructive tools on guests with guard tags or PVE's
+    /// `protection` flag.
+    pub fn set_guard(&mut self, guard: GuestGuard) {
+        self.guard = Some(Arc::new(guard));
+    }
+
+    /// Makes destructive tools return a preview and a confirmation token
+    /// on the first call, and act only when called again with that token.
+    pub fn enable_confirmation(&mut self, ttl: Duration) {
+        self.confirmations = Some(Arc::new(ConfirmationStore::new(ttl)));
+    }
+
+    /// Makes every mutating tool report the requests it would send
+    /// instead of sending them.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Records every tool call in the given audit log.
+    pub fn set_audit_log(&mut self, audit: AuditLog) {
+        self.audit = Some(Arc::new(audit));
+    }
+
+    /// Also stops the PVE task a cancelled request was waiting on.
+    pub fn set_stop_task_on_cancel(&mut self, stop: bool) {
+        self.stop_task_on_cancel = stop;
+    }
+
+    /// How often `cluster/resources` is polled while resources are
+    /// subscribed to.
+    pub fn set_subscription_poll_interval(&mut self, interval: Duration) {
+        self.subscriptions = Arc::new(Subscriptions::new(interval));
+    }
+
+    /// Serves the given prompts instead of only the built-in ones.
+    pub fn set_prompts(&mut self, prompts: PromptLibrary) {
+        self.prompts = Arc::new(prompts);
+    }
+
+    /// Sends log messages to the sessions registered here with
+    /// `logging/setLevel`; the same sessions must be given to the
+    /// `McpLogLayer`.
+    pub fn set_log_sessions(&mut self, sessions: Arc<LogSessions>) {
+        self.log_sessions = sessions;
+    }
+
+    /// Forgets the resource subscriptions and log level of a session that
+    /// has ended.
+    pub fn end_session(&self, session_id: Option<&str>) {
+        self.subscriptions.end_session(session_id);
+        self.log_sessions.end_session(session_id);
+    }
+
+    fn is_multi_cluster(&self) -> bool {
+        self.clusters.clients.len() > 1
+    }
+
+    /// Returns a view of this server whose handlers talk to the named
+    /// cluster, or to the default cluster when no name is given.
+    fn for_cluster(&self, name: Option<&str>) -> Result<Self> {
+        let name = name.unwrap_or(&self.clusters.default);
+        let client = self
+            .clusters
+            .clients
+            .get(name)
+            .cloned()
+            .ok_or(anyhow::anyhow!("Unknown cluster: {}", name))?;
+        Ok(Self {
+            client,
+            cluster: name.to_string(),
+            ..self.clone()
+        })
+    }
+
+    /// API metrics of every cluster in the Prometheus text format.
+    pub fn metrics(&self) -> String {
+        metrics::render(
+            self.clusters
+                .clients
+                .iter()
+                .map(|(name, client)| (name.as_str(), client.metrics())),
+        )
     }
 
     pub fn check_notification(&self) -> bool {
@@ -67,118 +671,175 @@ impl McpServer {
     }
 
     pub async fn run_stdio(&mut self) -> Result<()> {
-        let stdin = io::stdin();
-        let mut reader = stdin.lock();
-        let mut line = String::new();
-
-        loop {
-            line.clear();
-            let bytes = reader.read_line(&mut line)?;
-            if bytes == 0 {
-                break; // EOF
+        self.serve_stdio(tokio::io::stdin(), tokio::io::stdout())
+            .await
+    }
+
+    /// Serves newline-delimited JSON-RPC. Requests are handled concurrently;
+    /// their responses and notifications, as well as server-initiated
+    /// notifications, all go through a single writer task.
+    pub async fn serve_stdio<R, W>(&self, input: R, output: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Value>();
+        let writer = tokio::spawn(async move {
+            let mut output = output;
+            while let Some(message) = out_rx.recv().await {
+                let line = format!("{}\n", message);
+                output.write_all(line.as_bytes()).await?;
+                output.flush().await?;
+            }
+            Ok::<_, io::Error>(())
+        });
+
+        // Emits tool list changes as they happen rather than after the
+        // response of the request that caused them.
+        let watcher = tokio::spawn({
+            let server = self.clone();
+            let out = out_tx.clone();
+            async move {
+                loop {
+                    server.tools_changed.notified().await;
+                    if server.check_notification()
+                        && out
+                            .send(json!({
+                                "jsonrpc": "2.0",
+                                "method": "notifications/tools/list_changed"
+                            }))
+                            .is_err()
+                    {
+                        break;
+                    }
+                }
             }
+        });
 
+        let mut requests = JoinSet::new();
+        let mut lines = BufReader::new(input).lines();
+        while let Some(line) = lines.next_line().await? {
             let input = line.trim();
             if input.is_empty() {
                 continue;
             }
 
-            debug!("Received: {}", input);
-
-            match serde_json::from_str::<JsonRpcRequest>(input) {
-                Ok(req) => {
-                    let id = req.id.clone();
-                    let resp = self.handle_request(req).await;
-
-                    if let Some(req_id) = id {
-                        let json_resp = match resp {
-                            Ok(result) => JsonRpcResponse {
-                                jsonrpc: "2.0".to_string(),
-                                id: Some(req_id),
-                                result: Some(result),
-                                error: None,
-                            },
-                            Err(e) => {
-                                let (code, message, data) = if let Some(pve_err) =
-                                    e.downcast_ref::<crate::proxmox::ProxmoxError>()
-                                {
-                                    match pve_err {
-                                        crate::proxmox::error::ProxmoxError::Auth(_) => {
-                                            (-32001, pve_err.to_string(), None)
-                                        }
-                                        crate::proxmox::error::ProxmoxError::NotFound(_) => {
-                                            (-32004, pve_err.to_string(), None)
-                                        }
-                                        crate::proxmox::error::ProxmoxError::Timeout(_) => {
-                                            (-32002, pve_err.to_string(), None)
-                                        }
-                                        crate::proxmox::error::ProxmoxError::Api(status, msg) => {
-                                            let code = match status.as_u16() {
-                                                401 | 403 => -32001,
-                                                404 => -32004,
-                                                _ => -32603,
-                                            };
-                                            (
-                                                code,
-                                                format!("API Error {}: {}", status, msg),
-                                                Some(
-                                                    json!({ "status": status.as_u16(), "details": msg }),
-                                                ),
-                                            )
-                                        }
-                                        _ => (-32603, pve_err.to_string(), None),
-                                    }
-                                } else {
-                                    (-32603, e.to_string(), None)
-                                };
-
-                                JsonRpcResponse {
-                                    jsonrpc: "2.0".to_string(),
-                                    id: Some(req_id),
-                                    result: None,
-                                    error: Some(JsonRpcError {
-                                        code,
-                                        message,
-                                        data,
-                                    }),
-                                }
-                            }
-                        };
-
-                        let out = serde_json::to_string(&json_resp)?;
-                        println!("{}", out);
-                        io::stdout().flush()?;
-
-                        // Check for notification (e.g. tool list changed)
-                        if self.check_notification() {
-                            let notification = json!({
-                                "jsonrpc": "2.0",
-                                "method": "notifications/tools/list_changed"
-                            });
-                            let out = serde_json::to_string(&notification)?;
-                            println!("{}", out);
-                            io::stdout().flush()?;
+            let req = match parse_request(input) {
+                Ok(req) => req,
+                Err(resp) => {
+                    error!("Failed to parse JSON-RPC: {}", input);
+                    let _ = out_tx.send(resp);
+                    continue;
+                }
+            };
+            debug!("Received: {}", req.redacted());
+
+            let server = self.clone();
+            let out = out_tx.clone();
+            let ctx = RequestContext {
+                notifier: Some(out.clone()),
+                session_notifier: Some(out.clone()),
+                ..Default::default()
+            };
+            let registration = self.register_request(&req, &ctx);
+            requests.spawn(async move {
+                let id = req.id.clone();
+                let resp = server
+                    .handle_registered_request(req, &ctx, registration)
+                    .await;
+                match id {
+                    // A cancelled request gets no response
+                    Some(_) if resp.as_ref().is_err_and(is_cancelled) => {}
+                    Some(id) => match serde_json::to_value(json_rpc_response(id, resp)) {
+                        Ok(message) => {
+                            let _ = out.send(message);
                         }
-                    } else {
-                        // Notification, no response expected
+                        Err(e) => error!("Failed to serialize response: {}", e),
+                    },
+                    // Notification, no response expected
+                    None => {
                         if let Err(e) = resp {
                             error!("Error handling notification: {}", e);
                         }
                     }
                 }
-                Err(e) => {
-                    error!("Failed to parse JSON-RPC: {}", e);
-                    // Technically should send parse error if ID is known, but usually can't recover ID.
-                }
-            }
+            });
+            while requests.try_join_next().is_some() {}
         }
+
+        // EOF: let in-flight requests finish before the writer shuts down
+        while requests.join_next().await.is_some() {}
+        self.end_session(None);
+        watcher.abort();
+        let _ = watcher.await;
+        drop(out_tx);
+        writer.await??;
         Ok(())
     }
 
+    #[cfg(test)]
     pub async fn handle_request(&self, req: JsonRpcRequest) -> Result<Value> {
+        self.handle_request_with_context(req, &RequestContext::default())
+            .await
+    }
+
+    /// Handles one JSON-RPC message. Requests with an ID stay registered
+    /// while they run so `notifications/cancelled` can abort them.
+    pub async fn handle_request_with_context(
+        &self,
+        req: JsonRpcRequest,
+        ctx: &RequestContext,
+    ) -> Result<Value> {
+        let registration = self.register_request(&req, ctx);
+        self.handle_registered_request(req, ctx, registration).await
+    }
+
+    /// Registers a request with an ID so `notifications/cancelled` can abort
+    /// it. Transports that hand requests to another task register them
+    /// first, so that a cancellation sent right behind a request finds it.
+    pub fn register_request(
+        &self,
+        req: &JsonRpcRequest,
+        ctx: &RequestContext,
+    ) -> Option<Registration> {
+        let id = req.id.as_ref().filter(|_| req.method != "initialize")?;
+        Some(Registration::new(
+            &self.in_flight,
+            ctx.session_id.clone(),
+            id,
+        ))
+    }
+
+    /// Handles a request registered with `register_request`; the
+    /// registration ends when the request does.
+    pub async fn handle_registered_request(
+        &self,
+        req: JsonRpcRequest,
+        ctx: &RequestContext,
+        registration: Option<Registration>,
+    ) -> Result<Value> {
+        let request = registration.as_ref().map(|r| r.request.clone());
+        // Log messages go to the session that caused them
+        let span = tracing::info_span!("request", session = ctx.session_id.as_deref());
+        self.dispatch_request(req, ctx, request)
+            .instrument(span)
+            .await
+    }
+
+    async fn dispatch_request(
+        &self,
+        req: JsonRpcRequest,
+        ctx: &RequestContext,
+        request: Option<Arc<InFlightRequest>>,
+    ) -> Result<Value> {
         match req.method.as_str() {
             "initialize" => Ok(json!({
-                "protocolVersion": "2024-11-05",
+                "protocolVersion": negotiate_protocol_version(
+                    req.params
+                        .as_ref()
+                        .and_then(|p| p.get("protocolVersion"))
+                        .and_then(|v| v.as_str())
+                ),
                 "serverInfo": {
                     "name": "proxmox-mcp-rs",
                     "version": "0.1.0"
@@ -187,13 +848,40 @@ impl McpServer {
                     "tools": {
                         "listChanged": true
                     },
-                    "resources": {}
+                    "resources": {
+                        "subscribe": true
+                    },
+                    "prompts": {},
+                    "logging": {},
+                    "completions": {}
                 }
             })),
             "notifications/initialized" => {
                 info!("Client initialized");
                 Ok(Value::Null)
             }
+            "notifications/cancelled" => {
+                let params = req.params.unwrap_or_default();
+                let Some(id) = params.get("requestId") else {
+                    return Err(McpError::InvalidParams(
+                        "Missing requestId for notifications/cancelled".into(),
+                    )
+                    .into());
+                };
+                let reason = params.get("reason").and_then(|r| r.as_str());
+                match self
+                    .in_flight
+                    .get(&(ctx.session_id.clone(), id.to_string()))
+                {
+                    Some(request) => {
+                        info!("Cancelling request {}", id);
+                        request.cancel(reason);
+                    }
+                    // It may have finished in the meantime
+                    None => debug!("Cancellation for unknown request {}", id),
+                }
+                Ok(Value::Null)
+            }
             "ping" => Ok(json!({})),
             "tools/list" => Ok(json!({
                 "tools": self.get_tool_definitions()
@@ -202,42 +890,406 @@ impl McpServer {
                 if let Some(params) = req.params {
                     let name = params.get("name").and_then(|n| n.as_str()).unwrap_or("");
                     let args = params.get("arguments").unwrap_or(&Value::Null);
-                    self.call_tool(name, args).await
+                    let progress = params
+                        .get("_meta")
+                        .and_then(|m| m.get("progressToken"))
+                        .cloned()
+                        .zip(ctx.notifier.clone())
+                        .map(|(token, notifier)| ProgressReporter::new(token, notifier));
+                    let server = Self {
+                        progress,
+                        request: request.clone(),
+                        ..self.clone()
+                    };
+                    let started = std::time::Instant::now();
+                    let res = match &request {
+                        Some(request) => {
+                            tokio::select! {
+                                res = server.run_tool(name, args) => res,
+                                reason = request.cancelled() => {
+                                    self.stop_cancelled_task(request).await;
+                                    Err(McpError::Cancelled(reason).into())
+                                }
+                            }
+                        }
+                        None => server.run_tool(name, args).await,
+                    };
+                    if let Some(audit) = &self.audit {
+                        audit.record(&AuditRecord::new(ctx, name, args, &res, started.elapsed()));
+                    }
+                    let res = res.map(|(res, _)| res);
+                    match res {
+                        Err(e) if is_tool_failure(&e) => Ok(tool_error_result(&e)),
+                        res => res,
+                    }
                 } else {
-                    anyhow::bail!("Missing params for tools/call");
+                    Err(McpError::InvalidParams("Missing params for tools/call".into()).into())
                 }
             }
             "resources/list" => Ok(json!({
                 "resources": self.get_resource_definitions()
             })),
+            "resources/subscribe" | "resources/unsubscribe" => {
+                let Some(uri) = req
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("uri"))
+                    .and_then(|u| u.as_str())
+                else {
+                    return Err(
+                        McpError::InvalidParams(format!("Missing uri for {}", req.method)).into(),
+                    );
+                };
+                if req.method == "resources/unsubscribe" {
+                    self.subscriptions.unsubscribe(ctx.session_id.clone(), uri);
+                    return Ok(json!({}));
+                }
+                let Some(notifier) = ctx.session_notifier.clone() else {
+                    anyhow::bail!("Resource subscriptions are not available on this connection");
+                };
+                self.subscribe_resource(ctx.session_id.clone(), uri, notifier)
+                    .await?;
+                Ok(json!({}))
+            }
+            "resources/templates/list" => Ok(json!({
+                "resourceTemplates": self.get_resource_templates()
+            })),
             "resources/read" => {
                 if let Some(params) = req.params {
                     let uri = params.get("uri").and_then(|n| n.as_str()).unwrap_or("");
-                    self.handle_resource_read(uri).await
+                    match &request {
+                        Some(request) => {
+                            tokio::select! {
+                                res = self.handle_resource_read(uri) => res,
+                                reason = request.cancelled() => Err(McpError::Cancelled(reason).into()),
+                            }
+                        }
+                        None => self.handle_resource_read(uri).await,
+                    }
                 } else {
-                    anyhow::bail!("Missing params for resources/read");
+                    Err(McpError::InvalidParams("Missing params for resources/read".into()).into())
                 }
             }
+            "logging/setLevel" => {
+                let Some(level) = req
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("level"))
+                    .and_then(|l| l.as_str())
+                else {
+                    return Err(McpError::InvalidParams(
+                        "Missing level for logging/setLevel".into(),
+                    )
+                    .into());
+                };
+                let Some(severity) = parse_level(level) else {
+                    anyhow::bail!("Invalid log level: {}", level);
+                };
+                let Some(notifier) = ctx.session_notifier.clone() else {
+                    anyhow::bail!("Log messages are not available on this connection");
+                };
+                self.log_sessions
+                    .set_level(ctx.session_id.clone(), severity, notifier);
+                Ok(json!({}))
+            }
+            "completion/complete" => {
+                let params = req.params.unwrap_or_default();
+                let Some(argument) = params["argument"]["name"].as_str() else {
+                    return Err(McpError::InvalidParams(
+                        "Missing argument for completion/complete".into(),
+                    )
+                    .into());
+                };
+                let value = params["argument"]["value"].as_str().unwrap_or_default();
+                let context = &params["context"]["arguments"];
+                let candidates = self.completion_candidates(argument, context).await?;
+                Ok(json!({ "completion": complete(&candidates, value) }))
+            }
+            "prompts/list" => Ok(json!({
+                "prompts": self.get_prompt_definitions()
+            })),
+            "prompts/get" => {
+                let params = req.params.unwrap_or_default();
+                let Some(name) = params.get("name").and_then(|n| n.as_str()) else {
+                    return Err(
+                        McpError::InvalidParams("Missing name for prompts/get".into()).into(),
+                    );
+                };
+                let args = params
+                    .get("arguments")
+                    .and_then(|a| a.as_object())
+                    .cloned()
+                    .unwrap_or_default();
+                self.get_prompt(name, &args)
+            }
             _ => {
                 // Ignore unknown methods or return error?
                 // For MCP, unknown methods should probably be ignored if they are notifications,
                 // or error if request.
-                anyhow::bail!("Method not found: {}", req.method);
+                Err(McpError::MethodNotFound(req.method.clone()).into())
             }
         }
     }
 
+    /// Values `completion/complete` offers for a tool, prompt or resource
+    /// template argument, given the arguments already filled in. Fetched
+    /// candidates are cached briefly per cluster.
+    async fn completion_candidates(
+        &self,
+        argument: &str,
+        context: &Value,
+    ) -> Result<Vec<Candidate>> {
+        let server = self.for_cluster(context["cluster"].as_str())?;
+        let key = |kind: &str, context: &str| format!("{}/{}/{}", server.cluster, kind, context);
+
+        match argument {
+            "node" | "target_node" | "target" => {
+                self.completions
+                    .get_or_fetch(key("node", ""), server.completion_nodes())
+                    .await
+            }
+            "vmid" => {
+                self.completions
+                    .get_or_fetch(key("vmid", ""), async {
+                        let mut vms = server.scoped_vms().await?;
+                        vms.sort_by_key(|vm| vm.vmid);
+                        Ok(vms
+                            .into_iter()
+                            .map(|vm| Candidate {
+                                value: vm.vmid.to_string(),
+                                name: vm.name,
+                            })
+                            .collect())
+                    })
+                    .await
+            }
+            "snapname" => {
+                let Some(vmid) = context.get("vmid").and_then(parse_vmid) else {
+                    return Ok(Vec::new());
+                };
+                server.check_scope("", &json!({ "vmid": vmid })).await?;
+                self.completions
+                    .get_or_fetch(key("snapname", &vmid.to_string()), async {
+                        let (node, vm_type) = server.client.find_vm_location(vmid).await?;
+                        let snapshots = server.client.get_snapshots(&node, vmid, &vm_type).await?;
+                        Ok(snapshots
+                            .into_iter()
+                            .filter(|s| s.name != "current")
+                            .map(|s| Candidate::new(s.name))
+                            .collect())
+                    })
+                    .await
+            }
+            "storage" | "bridge" | "upid" => {
+                // Without a node, offer the values of every online node
+                let nodes = match context["node"].as_str() {
+                    Some(node) => {
+                        server.check_scope("", &json!({ "node": node })).await?;
+                        vec![node.to_string()]
+                    }
+                    None => self
+                        .completions
+                        .get_or_fetch(key("node", ""), server.completion_nodes())
+                        .await?
+                        .into_iter()
+                        .map(|c| c.value)
+                        .collect(),
+                };
+                let mut candidates = Vec::new();
+                for node in nodes {
+                    candidates.extend(
+                        self.completions
+                            .get_or_fetch(
+                                key(argument, &node),
+                                server.node_candidates(argument, &node),
+                            )
+                            .await?,
+                    );
+                }
+                Ok(candidates)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Online nodes in scope.
+    async fn completion_nodes(&self) -> Result<Vec<Candidate>> {
+        let mut nodes: Vec<String> = self
+            .client
+            .get_nodes()
+            .await?
+            .into_iter()
+            .filter(|n| n.status.as_deref().is_none_or(|s| s == "online"))
+            .map(|n| n.node)
+            .filter(|n| self.scope.check_node(n).is_ok())
+            .collect();
+        nodes.sort();
+        Ok(nodes.into_iter().map(Candidate::new).collect())
+    }
+
+    /// Storages, bridges or recent task UPIDs of one node.
+    async fn node_candidates(&self, argument: &str, node: &str) -> Result<Vec<Candidate>> {
+        let values: Vec<String> = match argument {
+            "storage" => self
+                .client
+                .get_storage_list(node)
+                .await?
+                .into_iter()
+                .map(|s| s.storage)
+                .collect(),
+            "bridge" => self
+                .client
+                .get_network_interfaces(node)
+                .await?
+                .iter()
+                .filter(|i| matches!(i["type"].as_str(), Some("bridge" | "OVSBridge")))
+                .filter_map(|i| i["iface"].as_str().map(str::to_string))
+                .collect(),
+            _ => self
+                .client
+                .list_tasks(node, None)
+                .await?
+                .into_iter()
+                .map(|t| t.upid)
+                .collect(),
+        };
+        Ok(values.into_iter().map(Candidate::new).collect())
+    }
+
+    /// Stops the PVE task a cancelled request was waiting on, if enabled.
+    async fn stop_cancelled_task(&self, request: &InFlightRequest) {
+        let Some(task) = request.task().filter(|_| self.stop_task_on_cancel) else {
+            return;
+        };
+        if self.dry_run {
+            info!(
+                "Dry run: not stopping task {} of cancelled request",
+                task.upid
+            );
+            return;
+        }
+        if let Err(e) = self.check_task_stop(&task).await {
+            warn!(
+                "Not stopping task {} of cancelled request: {:#}",
+                task.upid, e
+            );
+            return;
+        }
+        match task.client.stop_task(&task.node, &task.upid).await {
+            Ok(()) => info!("Stopped task {} of cancelled request", task.upid),
+            Err(e) => warn!("Failed to stop task {}: {:#}", task.upid, e),
+        }
+    }
+
+    /// Applies the checks of a mutating tool call to stopping a task: no
+    /// stops in read-only mode, the policy must permit `stop_task`, and the
+    /// task's node and guest must be in scope.
+    async fn check_task_stop(&self, task: &WatchedTask) -> Result<()> {
+        if self.read_only {
+            return Err(McpError::ReadOnly("stop_task".to_string()).into());
+        }
+        if !self.policy.permits("stop_task") {
+            return Err(McpError::PolicyDenied("stop_task".to_string()).into());
+        }
+        // UPID:node:pid:pstart:starttime:type:id:user: carries the VMID of guest tasks
+        let mut args = json!({ "node": task.node });
+        if let Some(vmid) = task
+            .upid
+            .split(':')
+            .nth(6)
+            .and_then(|id| id.parse::<i64>().ok())
+        {
+            args["vmid"] = json!(vmid);
+        }
+        let server = Self {
+            client: task.client.clone(),
+            ..self.clone()
+        };
+        server.check_scope("stop_task", &args).await
+    }
+
     fn get_resource_definitions(&self) -> Vec<Value> {
-        vec![
-            json!({
-                "uri": "proxmox://vms",
-                "name": "List of VMs",
-                "description": "A live list of all VMs and Containers",
-                "mimeType": "application/json"
-            }),
-            // Add more resources here, e.g., templates for nodes
-            // json!({ "uri": "proxmox://node/{node}/syslog", ... }) - Dynamic resources are harder to list statically
-        ]
+        if !self.is_multi_cluster() {
+            return RESOURCES
+                .iter()
+                .map(|(uri, name, description)| {
+                    json!({
+                        "uri": uri,
+                        "name": name,
+                        "description": description,
+                        "mimeType": "application/json"
+                    })
+                })
+                .collect();
+        }
+
+        self.clusters
+            .clients
+            .keys()
+            .flat_map(|cluster| {
+                RESOURCES.iter().map(move |(uri, name, description)| {
+                    json!({
+                        "uri": format!("{}?cluster={}", uri, cluster),
+                        "name": format!("{} ({})", name, cluster),
+                        "description": format!("{} in cluster '{}'", description, cluster),
+                        "mimeType": "application/json"
+                    })
+                })
+            })
+            .collect()
+    }
+
+    fn get_resource_templates(&self) -> Vec<Value> {
+        let cluster_param = if self.is_multi_cluster() {
+            "{?cluster}"
+        } else {
+            ""
+        };
+        RESOURCE_TEMPLATES
+            .iter()
+            .map(|(template, name, description)| {
+                json!({
+                    "uriTemplate": format!("{}{}", template, cluster_param),
+                    "name": name,
+                    "description": description,
+                    "mimeType": "application/json"
+                })
+            })
+            .collect()
+    }
+
+    fn get_prompt_definitions(&self) -> Vec<Value> {
+        let tools = self.all_tool_definitions();
+        self.prompts
+            .iter()
+            .filter(|p| self.is_prompt_permitted(p, &tools))
+            .map(|p| p.definition())
+            .collect()
+    }
+
+    fn get_prompt(&self, name: &str, args: &serde_json::Map<String, Value>) -> Result<Value> {
+        let prompt = self
+            .prompts
+            .get(name)
+            .filter(|p| self.is_prompt_permitted(p, &self.all_tool_definitions()))
+            .ok_or_else(|| McpError::InvalidParams(format!("Unknown prompt: {}", name)))?;
+        Ok(json!({
+            "description": prompt.description,
+            "messages": [{
+                "role": "user",
+                "content": { "type": "text", "text": prompt.render(args)? }
+            }]
+        }))
+    }
+
+    /// Whether read-only mode and the tool policy permit every tool the
+    /// prompt relies on.
+    fn is_prompt_permitted(&self, prompt: &Prompt, tools: &[Value]) -> bool {
+        prompt.tools.iter().all(|name| {
+            tools
+                .iter()
+                .any(|t| t["name"] == name.as_str() && self.is_permitted(t))
+        })
     }
 
     fn get_tool_definitions(&self) -> Vec<Value> {
@@ -248,6 +1300,7 @@ impl McpServer {
                     json!({
                         "name": "load_all_tools",
                         "description": "Load all Proxmox tools (VMs, containers, storage, etc.). Use this to access full functionality.",
+                        "annotations": { "readOnlyHint": true },
                         "inputSchema": {
                             "type": "object",
                             "properties": {},
@@ -257,6 +1310,7 @@ impl McpServer {
                     json!({
                         "name": "get_cluster_status",
                         "description": "Get cluster status information",
+                        "annotations": { "readOnlyHint": true },
                         "inputSchema": {
                             "type": "object",
                             "properties": {},
@@ -266,16 +1320,47 @@ impl McpServer {
                     json!({
                         "name": "list_nodes",
                         "description": "List all nodes in the Proxmox cluster",
+                        "annotations": { "readOnlyHint": true },
                         "inputSchema": {
                             "type": "object",
                             "properties": {},
                             "required": []
-                        }
+                        },
+                        "outputSchema": output_schema("nodes", list_of(NodeInfo::schema()))
                     }),
-                ];
+                ]
+                .into_iter()
+                .filter(|t| self.is_permitted(t))
+                .map(|mut t| {
+                    annotate(&mut t);
+                    t
+                })
+                .collect();
             }
         }
 
+        self.all_tool_definitions()
+            .into_iter()
+            .filter(|t| self.is_permitted(t))
+            .collect()
+    }
+
+    /// Whether read-only mode and the tool policy allow the given tool
+    /// definition to be listed and called.
+    fn is_permitted(&self, tool: &Value) -> bool {
+        let name = tool["name"].as_str().unwrap_or_default();
+        if name == "describe_policy" {
+            return true;
+        }
+        if self.read_only && tool["annotations"]["readOnlyHint"] != true {
+            return false;
+        }
+        self.policy.permits(name)
+    }
+
+    /// Every tool this server implements, regardless of lazy loading or
+    /// read-only mode.
+    fn all_tool_definitions(&self) -> Vec<Value> {
         let mut tools = Vec::new();
         tools.extend(self.tool_defs_cluster());
         tools.extend(self.tool_defs_vm_lifecycle());
@@ -286,55 +1371,602 @@ impl McpServer {
         tools.extend(self.tool_defs_access());
         tools.extend(self.tool_defs_ha());
         tools.extend(self.tool_defs_misc());
+
+        if self.guard.as_ref().is_some_and(|g| g.allow_override) {
+            for tool in tools
+                .iter_mut()
+                .filter(|t| GuestGuard::is_guarded_tool(t["name"].as_str().unwrap_or_default()))
+            {
+                tool["inputSchema"]["properties"][OVERRIDE_ARG] = json!({
+                    "type": "boolean",
+                    "description": "Act even if the guest is protected by a guard tag or the PVE protection flag"
+                });
+            }
+        }
+
+        for tool in tools
+            .iter_mut()
+            .filter(|t| t["annotations"]["readOnlyHint"] == false)
+        {
+            tool["inputSchema"]["properties"]["dry_run"] = json!({
+                "type": "boolean",
+                "description": "Return the API requests this call would send without sending them"
+            });
+        }
+        for tool in tools
+            .iter_mut()
+            .filter(|t| t["annotations"]["readOnlyHint"] == true)
+        {
+            tool["inputSchema"]["properties"]["no_cache"] = json!({
+                "type": "boolean",
+                "description": "Read current data from PVE instead of a cached response"
+            });
+        }
+
+        if self.confirmations.is_some() {
+            for tool in tools.iter_mut().filter(|t| {
+                ConfirmationStore::requires_confirmation(t["name"].as_str().unwrap_or_default())
+            }) {
+                tool["inputSchema"]["properties"][CONFIRM_ARG] = json!({
+                    "type": "string",
+                    "description": "Token from the preview returned by the first call; required to actually perform the action"
+                });
+            }
+        }
+
+        if self.is_multi_cluster() {
+            let names: Vec<&String> = self.clusters.clients.keys().collect();
+            for tool in tools.iter_mut().filter(|t| t["name"] != "list_clusters") {
+                tool["inputSchema"]["properties"]["cluster"] = json!({
+                    "type": "string",
+                    "enum": names,
+                    "description": format!("Target cluster (default: {})", self.clusters.default)
+                });
+            }
+        }
+        tools.iter_mut().for_each(annotate);
         tools
     }
 
     async fn handle_resource_read(&self, uri: &str) -> Result<Value> {
-        match uri {
-            "proxmox://vms" => {
-                let vms = self.client.get_all_vms().await?;
-                let content = serde_json::to_string_pretty(&vms)?;
-                Ok(json!({
-                    "contents": [{
-                        "uri": uri,
-                        "mimeType": "application/json",
-                        "text": content
-                    }]
-                }))
+        let (path, cluster) = match uri.split_once("?cluster=") {
+            Some((path, cluster)) => (path, Some(cluster)),
+            None => (uri, None),
+        };
+        let server = self.for_cluster(cluster)?;
+        let not_found = || anyhow::anyhow!("Resource not found: {}", uri);
+
+        let segments: Vec<String> = path
+            .strip_prefix("proxmox://")
+            .ok_or_else(not_found)?
+            .split('/')
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        let data = match segments.as_slice() {
+            ["vms"] => json!(server.scoped_vms().await?),
+            ["node", node, "status"] => {
+                server.check_scope("", &json!({ "node": node })).await?;
+                server.client.get_node_status(node).await?
+            }
+            ["node", node, "syslog"] => {
+                server.check_scope("", &json!({ "node": node })).await?;
+                json!(server.client.get_node_syslog(node, None).await?)
+            }
+            ["guest", vmid, kind @ ("config" | "snapshots")] => {
+                let vmid: i64 = vmid.parse().map_err(|_| not_found())?;
+                server.check_scope("", &json!({ "vmid": vmid })).await?;
+                let (node, vm_type) = server.client.find_vm_location(vmid).await?;
+                if *kind == "config" {
+                    server.client.get_vm_config(&node, vmid, &vm_type).await?
+                } else {
+                    json!(server.client.get_snapshots(&node, vmid, &vm_type).await?)
+                }
             }
-            _ => anyhow::bail!("Resource not found: {}", uri),
+            ["task", node, upid, "log"] => {
+                server.check_scope("", &json!({ "node": node })).await?;
+                json!(server.client.get_task_log(node, upid).await?)
+            }
+            ["storage", node, storage, "content"] => {
+                server.check_scope("", &json!({ "node": node })).await?;
+                let mut content = server
+                    .client
+                    .get_storage_content(node, storage, None)
+                    .await?;
+                if !server.scope.is_unrestricted() {
+                    let vms = server.client.get_all_vms().await?;
+                    content.retain(|item| match item.get("vmid").and_then(parse_vmid) {
+                        Some(id) => vms
+                            .iter()
+                            .find(|vm| vm.vmid == id)
+                            .is_some_and(|vm| server.vm_in_scope(vm)),
+                        None => true,
+                    });
+                }
+                json!(content)
+            }
+            ["cluster", "ha"] => {
+                let mut resources = server.client.get_ha_resources().await?;
+                if !server.scope.is_unrestricted() {
+                    let vms = server.client.get_all_vms().await?;
+                    resources.retain(|r| {
+                        target_vmid("ha_resource", r)
+                            .and_then(|id| vms.iter().find(|vm| vm.vmid == id))
+                            .is_some_and(|vm| server.vm_in_scope(vm))
+                    });
+                }
+                json!({
+                    "resources": resources,
+                    "groups": server.client.get_ha_groups().await?
+                })
+            }
+            _ => return Err(not_found()),
+        };
+
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "application/json",
+                "text": serde_json::to_string_pretty(&data)?
+            }]
+        }))
+    }
+
+    /// Subscribes a session to updates of `proxmox://vms` or a guest or
+    /// node resource, starting the poller if it is not running yet.
+    async fn subscribe_resource(
+        &self,
+        session_id: Option<String>,
+        uri: &str,
+        notifier: Notifier,
+    ) -> Result<()> {
+        let (path, cluster) = match uri.split_once("?cluster=") {
+            Some((path, cluster)) => (path, Some(cluster)),
+            None => (uri, None),
+        };
+        let server = self.for_cluster(cluster)?;
+        match subscribable(path) {
+            Some(Target::Vms) => {}
+            Some(Target::Guest(vmid)) => server.check_scope("", &json!({ "vmid": vmid })).await?,
+            Some(Target::Node(node)) => server.check_scope("", &json!({ "node": node })).await?,
+            None => anyhow::bail!("Resource does not support subscriptions: {}", uri),
+        }
+
+        self.subscriptions.subscribe(session_id, uri, notifier);
+        let poller = self.clone();
+        self.subscriptions
+            .ensure_poller(|| poller.poll_subscribed_resources());
+        Ok(())
+    }
+
+    /// Polls `cluster/resources` of every cluster with subscriptions and
+    /// notifies subscribers of the resources that changed since the last
+    /// round. Stops once nothing is subscribed.
+    async fn poll_subscribed_resources(self) {
+        let mut snapshots: HashMap<String, Snapshot> = HashMap::new();
+        while !self.subscriptions.poller_idle() {
+            let uris = self.subscriptions.uris();
+            let clusters: BTreeSet<&str> = uris
+                .iter()
+                .map(|uri| {
+                    uri.split_once("?cluster=")
+                        .map_or(self.clusters.default.as_str(), |(_, c)| c)
+                })
+                .collect();
+
+            for cluster in clusters {
+                let Some(client) = self.clusters.clients.get(cluster) else {
+                    continue;
+                };
+                let resources = match client.get_resources().await {
+                    Ok(resources) => resources,
+                    Err(e) => {
+                        warn!("Failed to poll resources of cluster '{}': {:#}", cluster, e);
+                        continue;
+                    }
+                };
+                let snapshot = Snapshot::new(resources.iter().filter(|r| match r.vmid {
+                    Some(vmid) => self.scope.permits_guest(
+                        vmid,
+                        Some(&r.node),
+                        r.pool.as_deref(),
+                        r.tags.as_deref(),
+                    ),
+                    None => self.scope.check_node(&r.node).is_ok(),
+                }));
+                let Some(previous) = snapshots.insert(cluster.to_string(), snapshot) else {
+                    continue;
+                };
+                let changes = previous.changes(&snapshots[cluster]);
+                if changes.is_empty() {
+                    continue;
+                }
+                debug!("Resources changed in cluster '{}': {:?}", cluster, changes);
+                self.subscriptions.notify(|uri| {
+                    let (path, uri_cluster) = match uri.split_once("?cluster=") {
+                        Some((path, c)) => (path, c),
+                        None => (uri, self.clusters.default.as_str()),
+                    };
+                    uri_cluster == cluster && changes.affects(path)
+                });
+            }
+
+            tokio::time::sleep(self.subscriptions.interval()).await;
         }
     }
 
+    #[cfg(test)]
     pub async fn call_tool(&self, name: &str, args: &Value) -> Result<Value> {
+        self.run_tool(name, args).await.map(|(res, _)| res)
+    }
+
+    /// Runs a tool and reports whether it actually ran or only returned a
+    /// confirmation request or a dry-run plan.
+    async fn run_tool(&self, name: &str, args: &Value) -> Result<(Value, Outcome)> {
+        let tool = self.tool_definition(name);
+        let writes = tool
+            .as_ref()
+            .and_then(|t| t["annotations"]["readOnlyHint"].as_bool())
+            == Some(false);
+        if self.read_only && writes {
+            return Err(McpError::ReadOnly(name.to_string()).into());
+        }
+        if name != "describe_policy" && !self.policy.permits(name) {
+            return Err(McpError::PolicyDenied(name.to_string()).into());
+        }
+        if let Some(tool) = &tool {
+            let errors = validate(&tool["inputSchema"], args);
+            if !errors.is_empty() {
+                return Err(McpError::InvalidArguments(errors).into());
+            }
+        }
+
+        let cluster = args.get("cluster").and_then(|v| v.as_str());
+        let mut server = self.for_cluster(cluster)?;
+
+        // Several handlers forward their arguments to PVE verbatim, so the
+        // routing, override, confirmation and dry-run arguments must not leak
+        // into the request body.
+        let mut args = args.clone();
+        let mut override_guard = false;
+        let mut confirmation = None;
+        let mut dry_run = self.dry_run;
+        let mut no_cache = false;
+        if let Some(obj) = args.as_object_mut() {
+            obj.remove("cluster");
+            confirmation = obj.remove(CONFIRM_ARG);
+            override_guard = obj.remove(OVERRIDE_ARG).and_then(|v| v.as_bool()) == Some(true);
+            dry_run |= obj.remove("dry_run").and_then(|v| v.as_bool()) == Some(true);
+            no_cache = obj.remove("no_cache").and_then(|v| v.as_bool()) == Some(true);
+        }
+        if no_cache {
+            server.client = server.client.without_cache();
+        }
+        let dry_run = dry_run && writes;
+        if dry_run {
+            server.client = server.client.with_dry_run();
+        }
+
+        let span = tracing::info_span!("tool", name, cluster = %server.cluster);
+        let res: Result<(Value, Outcome)> = async {
+            server.check_scope(name, &args).await?;
+            server.check_guard(name, &args, override_guard).await?;
+            if let Some(store) = self
+                .confirmations
+                .as_ref()
+                .filter(|_| !dry_run && ConfirmationStore::requires_confirmation(name))
+            {
+                // The override is part of what gets confirmed.
+                let bound = json!({ "args": &args, OVERRIDE_ARG: override_guard });
+                match confirmation.as_ref().and_then(|v| v.as_str()) {
+                    Some(token) => store
+                        .redeem(token, name, &server.cluster, &bound)
+                        .map_err(McpError::Confirmation)?,
+                    None => {
+                        let preview = server.confirmation_preview(name, &args).await?;
+                        let token = store.issue(name, &server.cluster, &bound);
+                        return Ok((
+                            confirmation_required(name, &preview, &token, store.ttl()),
+                            Outcome::ConfirmationRequired,
+                        ));
+                    }
+                }
+            }
+            let res = server.dispatch_tool(name, &args).await?;
+            if !dry_run {
+                let res = match server
+                    .progress
+                    .as_ref()
+                    .filter(|_| ProgressReporter::tracks(name))
+                {
+                    Some(progress) => server.follow_task(res, progress).await?,
+                    None => res,
+                };
+                return Ok((res, Outcome::Success));
+            }
+            let plan = json!({
+                "dry_run": true,
+                "tool": name,
+                "requests": server.client.planned_requests()
+            });
+            let text = serde_json::to_string_pretty(&plan)?;
+            Ok((
+                json!({ "content": [{ "type": "text", "text": text }] }),
+                Outcome::DryRun,
+            ))
+        }
+        .instrument(span)
+        .await;
+        if self.is_multi_cluster() {
+            res.map_err(|e| e.context(ClusterContext(server.cluster.clone())))
+        } else {
+            res
+        }
+    }
+
+    /// Follows the task a tool started until it stops, then appends its
+    /// final status to the tool's result.
+    async fn follow_task(&self, res: Value, progress: &ProgressReporter) -> Result<Value> {
+        let Some(upid) = find_upid(&res) else {
+            return Ok(res);
+        };
+        if let (Some(request), Some(node)) = (&self.request, upid.split(':').nth(1)) {
+            request.watch_task(&self.client, node, &upid);
+        }
+        let status = progress.follow_task(&self.client, &upid).await?;
+        let exit_status = status.exitstatus.as_deref().unwrap_or("unknown");
+        let started = res["content"][0]["text"].as_str().unwrap_or_default();
+        Ok(
+            json!({ "content": [{ "type": "text", "text": format!("{}\nTask finished with status: {}\nFull details:\n{}", started, exit_status, serde_json::to_string_pretty(&status)?) }] }),
+        )
+    }
+
+    /// Refuses calls that address a node or guest outside the configured
+    /// scope. Existing guests are resolved through `cluster/resources`;
+    /// guests that do not exist yet are checked against the VMID ranges and
+    /// the `pool`/`tags` they are created with.
+    async fn check_scope(&self, name: &str, args: &Value) -> Result<()> {
+        if self.scope.is_unrestricted() {
+            return Ok(());
+        }
+        let out_of_scope = |reason: String| anyhow::Error::from(McpError::OutOfScope(reason));
+
+        for key in ["node", "target", "target_node"] {
+            if let Some(node) = args.get(key).and_then(|v| v.as_str()) {
+                self.scope.check_node(node).map_err(out_of_scope)?;
+            }
+        }
+        if let Some(newid) = args.get("newid").and_then(parse_vmid) {
+            self.scope.check_vmid(newid).map_err(out_of_scope)?;
+        }
+
+        // Pool membership and ACL changes act on guests too
+        let mut vmids: Vec<i64> = target_vmid(name, args).into_iter().collect();
+        let invalid = |id: &str| {
+            anyhow::Error::from(McpError::InvalidParams(format!("Invalid VMID '{}'", id)))
+        };
+        match name {
+            "update_pool" => {
+                let list = args.get("vms").and_then(|v| v.as_str()).unwrap_or_default();
+                for id in list.split([',', ';', ' ']).filter(|id| !id.is_empty()) {
+                    vmids.push(id.parse().map_err(|_| invalid(id))?);
+                }
+            }
+            "update_acl" => {
+                let path = args
+                    .get("path")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let mut segments = path.trim_matches('/').split('/');
+                match (segments.next(), segments.next()) {
+                    (Some("vms"), Some(id)) => vmids.push(id.parse().map_err(|_| invalid(id))?),
+                    (Some("nodes"), Some(node)) => {
+                        self.scope.check_node(node).map_err(out_of_scope)?
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        if vmids.is_empty() {
+            return Ok(());
+        }
+
+        let resources = self.client.get_resources().await?;
+        for vmid in vmids {
+            let check = match resources.iter().find(|r| r.vmid == Some(vmid)) {
+                Some(r) => self.scope.check_guest(
+                    vmid,
+                    Some(&r.node),
+                    r.pool.as_deref(),
+                    r.tags.as_deref(),
+                ),
+                None => self.scope.check_guest(
+                    vmid,
+                    None,
+                    args.get("pool").and_then(|v| v.as_str()),
+                    args.get("tags").and_then(|v| v.as_str()),
+                ),
+            };
+            check.map_err(out_of_scope)?;
+        }
+        Ok(())
+    }
+
+    /// Refuses guarded tools on guests that carry a guard tag or the PVE
+    /// `protection` flag, unless an override was requested and the
+    /// operator allows overrides.
+    async fn check_guard(&self, name: &str, args: &Value, override_guard: bool) -> Result<()> {
+        let Some(guard) = &self.guard else {
+            return Ok(());
+        };
+        if !GuestGuard::is_guarded_tool(name) {
+            return Ok(());
+        }
+        let Some(vmid) = target_vmid(name, args) else {
+            return Ok(());
+        };
+
+        let (node, vm_type) = self.client.find_vm_location(vmid).await?;
+        let config = self.client.get_vm_config(&node, vmid, &vm_type).await?;
+        let Some(reason) = guard.reason(&config) else {
+            return Ok(());
+        };
+
+        if override_guard && guard.allow_override {
+            warn!(
+                "Guard overridden: running '{}' on guest {} which {}",
+                name, vmid, reason
+            );
+            return Ok(());
+        }
+        let hint = if guard.allow_override {
+            " without override_guard=true"
+        } else {
+            ""
+        };
+        Err(McpError::Guarded {
+            tool: name.to_string(),
+            vmid,
+            reason,
+            hint,
+        }
+        .into())
+    }
+
+    /// Describes what a destructive call would act on: the guest's current
+    /// config and disk size, a storage's config and usage, or the current
+    /// definition of the pool, user, role, HA resource or replication job.
+    async fn confirmation_preview(&self, name: &str, args: &Value) -> Result<Value> {
+        let mut preview = json!({ "tool": name, "arguments": args });
+        let id = |key: &str| {
+            args.get(key)
+                .and_then(|v| v.as_str())
+                .ok_or(anyhow::anyhow!("Missing {}", key))
+        };
+        // The entry of a listing whose `key` matches the argument of that name
+        let find = |list: Vec<Value>, key: &str| -> Result<Value> {
+            let id = id(key)?;
+            Ok(list
+                .into_iter()
+                .find(|v| v[key] == id)
+                .unwrap_or(Value::Null))
+        };
+
+        if let Some(vmid) = target_vmid(name, args) {
+            let resources = self.client.get_resources().await?;
+            let guest = resources
+                .iter()
+                .find(|r| r.vmid == Some(vmid))
+                .ok_or(anyhow::anyhow!("VMID {} not found", vmid))?;
+            let config = self
+                .client
+                .get_vm_config(&guest.node, vmid, &guest.res_type)
+                .await?;
+            preview["target"] = json!({
+                "vmid": vmid,
+                "name": guest.name,
+                "type": guest.res_type,
+                "node": guest.node,
+                "status": guest.status,
+                "disk_size_bytes": guest.maxdisk,
+                "config": config
+            });
+        }
+
         match name {
+            "delete_storage" => {
+                let storage = id("storage")?;
+                let config = self
+                    .client
+                    .get_cluster_storage()
+                    .await?
+                    .into_iter()
+                    .find(|s| s["storage"] == storage);
+                let usage: Vec<Value> = self
+                    .client
+                    .get_resources()
+                    .await?
+                    .into_iter()
+                    .filter(|r| r.res_type == "storage" && r.storage.as_deref() == Some(storage))
+                    .map(|r| {
+                        json!({ "node": r.node, "used_bytes": r.disk, "size_bytes": r.maxdisk })
+                    })
+                    .collect();
+                preview["target"] = json!({ "storage": storage, "config": config, "usage": usage });
+            }
+            "delete_pool" => {
+                let poolid = id("poolid")?;
+                let pool = self.client.get_pool_details(poolid).await?;
+                preview["target"] = json!({ "pool": poolid, "config": pool });
+            }
+            "delete_user" => {
+                preview["target"] = find(self.client.get_users().await?, "userid")?;
+            }
+            "delete_role" => {
+                preview["target"] = find(self.client.get_roles().await?, "roleid")?;
+            }
+            "remove_ha_resource" => {
+                preview["ha_resource"] = find(self.client.get_ha_resources().await?, "sid")?;
+            }
+            "delete_replication_job" => {
+                let jobs = self.client.get_replication_jobs().await?;
+                preview["replication_job"] = find(jobs, "id")?;
+            }
+            _ => {}
+        }
+        Ok(preview)
+    }
+
+    fn vm_in_scope(&self, vm: &VmInfo) -> bool {
+        self.scope.permits_guest(
+            vm.vmid,
+            vm.node.as_deref(),
+            vm.pool.as_deref(),
+            vm.tags.as_deref(),
+        )
+    }
+
+    async fn scoped_vms(&self) -> Result<Vec<VmInfo>> {
+        let vms = self.client.get_all_vms().await?;
+        Ok(vms.into_iter().filter(|vm| self.vm_in_scope(vm)).collect())
+    }
+
+    /// The definition of the named tool, whether or not it is currently
+    /// listed.
+    fn tool_definition(&self, name: &str) -> Option<Value> {
+        self.all_tool_definitions()
+            .into_iter()
+            .find(|t| t["name"] == name)
+    }
+
+    async fn dispatch_tool(&self, name: &str, args: &Value) -> Result<Value> {
+        match name {
+            "list_clusters" => self.handle_list_clusters(),
+            "describe_policy" => self.handle_describe_policy(),
             "load_all_tools" => {
                 let mut state = self.state.lock().unwrap();
                 state.tools_loaded = true;
                 state.should_notify = true;
+                self.tools_changed.notify_one();
                 Ok(json!({ "content": [{ "type": "text", "text": "All tools loaded." }] }))
             }
             "list_nodes" => {
                 let nodes = self.client.get_nodes().await?;
-                Ok(
-                    json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&nodes)? }] }),
-                )
+                structured_result("nodes", &nodes)
             }
             "list_vms" => {
-                let vms = self.client.get_all_vms().await?;
-                Ok(
-                    json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&vms)? }] }),
-                )
+                let vms = self.scoped_vms().await?;
+                structured_result("vms", &vms)
             }
             "list_containers" => {
-                let vms = self.client.get_all_vms().await?;
+                let vms = self.scoped_vms().await?;
                 let containers: Vec<_> = vms
                     .into_iter()
                     .filter(|vm| vm.vm_type.as_deref() == Some("lxc"))
                     .collect();
-                Ok(
-                    json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&containers)? }] }),
-                )
+                structured_result("containers", &containers)
             }
             "start_vm" => self.handle_vm_action(args, "start", None).await,
             "start_container" => self.handle_vm_action(args, "start", Some("lxc")).await,
@@ -473,10 +2105,51 @@ impl McpServer {
             "remove_vm_device" => self.handle_remove_vm_device(args).await,
             "add_lxc_mountpoint" => self.handle_add_lxc_mountpoint(args).await,
             "remove_lxc_mountpoint" => self.handle_remove_lxc_mountpoint(args).await,
-            _ => anyhow::bail!("Unknown tool: {}", name),
+            _ => Err(McpError::InvalidParams(format!("Unknown tool: {}", name)).into()),
         }
     }
 
+    fn handle_list_clusters(&self) -> Result<Value> {
+        let clusters: Vec<Value> = self
+            .clusters
+            .clients
+            .iter()
+            .map(|(name, client)| {
+                json!({
+                    "name": name,
+                    "host": client.base_url.host_str(),
+                    "port": client.base_url.port(),
+                    "default": *name == self.clusters.default,
+                    "available": !client.login_pending(),
+                    "endpoints": client.endpoint_health()
+                })
+            })
+            .collect();
+        Ok(
+            json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&clusters)? }] }),
+        )
+    }
+
+    fn handle_describe_policy(&self) -> Result<Value> {
+        let permitted: Vec<Value> = self
+            .all_tool_definitions()
+            .into_iter()
+            .filter(|t| self.is_permitted(t))
+            .map(|t| t["name"].clone())
+            .collect();
+        let policy = json!({
+            "read_only": self.read_only,
+            "allow": self.policy.allow,
+            "deny": self.policy.deny,
+            "scope": *self.scope,
+            "guard": self.guard.as_deref(),
+            "permitted_tools": permitted
+        });
+        Ok(
+            json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&policy)? }] }),
+        )
+    }
+
     async fn handle_add_lxc_mountpoint(&self, args: &Value) -> Result<Value> {
         let node = args
             .get("node")
@@ -1240,9 +2913,7 @@ impl McpServer {
             .ok_or(anyhow::anyhow!("Missing node"))?;
 
         let storage = self.client.get_storage_list(node).await?;
-        Ok(
-            json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&storage)? }] }),
-        )
+        structured_result("storage", &storage)
     }
 
     async fn handle_list_isos(&self, args: &Value) -> Result<Value> {
@@ -1285,9 +2956,7 @@ impl McpServer {
             .ok_or(anyhow::anyhow!("Missing upid"))?;
 
         let status = self.client.get_task_status(node, upid).await?;
-        Ok(
-            json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&status)? }] }),
-        )
+        structured_result("task", &status)
     }
 
     async fn handle_list_tasks(&self, args: &Value) -> Result<Value> {
@@ -1298,9 +2967,7 @@ impl McpServer {
         let limit = args.get("limit").and_then(|v| v.as_u64());
 
         let tasks = self.client.list_tasks(node, limit).await?;
-        Ok(
-            json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&tasks)? }] }),
-        )
+        structured_result("tasks", &tasks)
     }
 
     async fn handle_wait_for_task(&self, args: &Value) -> Result<Value> {
@@ -1313,16 +2980,17 @@ impl McpServer {
             .and_then(|v| v.as_str())
             .ok_or(anyhow::anyhow!("Missing upid"))?;
         let timeout = args.get("timeout").and_then(|v| v.as_u64()).unwrap_or(60);
+        if let Some(request) = &self.request {
+            request.watch_task(&self.client, node, upid);
+        }
 
         let status = self.client.wait_for_task(node, upid, timeout).await?;
-        let exit_status = status
-            .get("exitstatus")
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown");
+        let exit_status = status.exitstatus.as_deref().unwrap_or("unknown");
 
-        Ok(
-            json!({ "content": [{ "type": "text", "text": format!("Task finished with status: {}\nFull details:\n{}", exit_status, serde_json::to_string_pretty(&status)?) }] }),
-        )
+        Ok(json!({
+            "content": [{ "type": "text", "text": format!("Task finished with status: {}\nFull details:\n{}", exit_status, serde_json::to_string_pretty(&status)?) }],
+            "structuredContent": { "task": status }
+        }))
     }
 
     async fn handle_list_backups(&self, args: &Value) -> Result<Value> {
@@ -1336,7 +3004,16 @@ impl McpServer {
             .ok_or(anyhow::anyhow!("Missing storage"))?;
         let vmid = args.get("vmid").and_then(|v| v.as_i64());
 
-        let backups = self.client.get_backups(node, storage, vmid).await?;
+        let mut backups = self.client.get_backups(node, storage, vmid).await?;
+        if !self.scope.is_unrestricted() {
+            let vms = self.client.get_all_vms().await?;
+            backups.retain(|b| {
+                b.get("vmid")
+                    .and_then(parse_vmid)
+                    .and_then(|id| vms.iter().find(|vm| vm.vmid == id))
+                    .is_some_and(|vm| self.vm_in_scope(vm))
+            });
+        }
         Ok(
             json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&backups)? }] }),
         )
@@ -1464,9 +3141,7 @@ impl McpServer {
         let vm_type = args.get("type").and_then(|v| v.as_str()).unwrap_or("qemu");
 
         let snapshots = self.client.get_snapshots(node, vmid, vm_type).await?;
-        Ok(
-            json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&snapshots)? }] }),
-        )
+        structured_result("snapshots", &snapshots)
     }
 
     async fn handle_snapshot_create(&self, args: &Value) -> Result<Value> {
@@ -2135,18 +3810,31 @@ impl McpServer {
 
     fn tool_defs_cluster(&self) -> Vec<Value> {
         vec![
+            json!({
+                "name": "list_clusters",
+                "description": "List the Proxmox clusters this server is connected to",
+                "annotations": { "readOnlyHint": true },
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                },
+            }),
             json!({
                 "name": "list_nodes",
                 "description": "List all nodes in the Proxmox cluster",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
                     "required": []
-                }
+                },
+                "outputSchema": output_schema("nodes", list_of(NodeInfo::schema()))
             }),
             json!({
                 "name": "get_cluster_status",
                 "description": "Get cluster status information",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -2156,6 +3844,7 @@ impl McpServer {
             json!({
                 "name": "get_cluster_log",
                 "description": "Read cluster log",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2167,6 +3856,7 @@ impl McpServer {
             json!({
                 "name": "get_node_stats",
                 "description": "Get RRD statistics for a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2180,6 +3870,7 @@ impl McpServer {
             json!({
                 "name": "create_cluster",
                 "description": "Create a new cluster",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2191,6 +3882,7 @@ impl McpServer {
             json!({
                 "name": "get_cluster_join_info",
                 "description": "Get the join info for the current cluster",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -2200,6 +3892,7 @@ impl McpServer {
             json!({
                 "name": "join_cluster",
                 "description": "Join an existing cluster",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2218,24 +3911,29 @@ impl McpServer {
             json!({
                 "name": "list_vms",
                 "description": "List all VMs and containers across all nodes",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
                     "required": []
-                }
+                },
+                "outputSchema": output_schema("vms", list_of(VmInfo::schema()))
             }),
             json!({
                 "name": "list_containers",
                 "description": "List all LXC containers across all nodes",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
                     "required": []
-                }
+                },
+                "outputSchema": output_schema("containers", list_of(VmInfo::schema()))
             }),
             json!({
                 "name": "start_vm",
                 "description": "Start a VM or container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2249,6 +3947,7 @@ impl McpServer {
             json!({
                 "name": "start_container",
                 "description": "Start an LXC container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2261,6 +3960,7 @@ impl McpServer {
             json!({
                 "name": "stop_vm",
                 "description": "Stop (power off) a VM or container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2274,6 +3974,7 @@ impl McpServer {
             json!({
                 "name": "stop_container",
                 "description": "Stop (power off) an LXC container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2286,6 +3987,7 @@ impl McpServer {
             json!({
                 "name": "shutdown_vm",
                 "description": "Gracefully shutdown a VM or container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2299,6 +4001,7 @@ impl McpServer {
             json!({
                 "name": "shutdown_container",
                 "description": "Gracefully shutdown an LXC container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2311,6 +4014,7 @@ impl McpServer {
             json!({
                 "name": "reboot_vm",
                 "description": "Reboot a VM or container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2324,6 +4028,7 @@ impl McpServer {
             json!({
                 "name": "create_vm",
                 "description": "Create a new QEMU VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2342,6 +4047,7 @@ impl McpServer {
             json!({
                 "name": "create_container",
                 "description": "Create a new LXC Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2360,6 +4066,7 @@ impl McpServer {
             json!({
                 "name": "delete_vm",
                 "description": "Delete a QEMU VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2372,6 +4079,7 @@ impl McpServer {
             json!({
                 "name": "delete_container",
                 "description": "Delete an LXC Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2384,6 +4092,7 @@ impl McpServer {
             json!({
                 "name": "reset_vm",
                 "description": "Reset (Stop and Start) a VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2395,6 +4104,7 @@ impl McpServer {
             json!({
                 "name": "reset_container",
                 "description": "Reset (Stop and Start) a Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2406,6 +4116,7 @@ impl McpServer {
             json!({
                 "name": "clone_vm",
                 "description": "Clone a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2423,6 +4134,7 @@ impl McpServer {
             json!({
                 "name": "migrate_vm",
                 "description": "Migrate a VM or Container to another node",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2438,6 +4150,7 @@ impl McpServer {
             json!({
                 "name": "get_vm_config",
                 "description": "Get the configuration of a VM or Container",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2451,6 +4164,7 @@ impl McpServer {
             json!({
                 "name": "get_console_url",
                 "description": "Get the URL for the Proxmox web console (NoVNC, xterm.js, or Spice)",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2465,6 +4179,7 @@ impl McpServer {
             json!({
                 "name": "get_vm_stats",
                 "description": "Get RRD statistics for a VM or Container",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2485,6 +4200,7 @@ impl McpServer {
             json!({
                 "name": "update_vm_resources",
                 "description": "Update VM hardware configuration (cores, memory, sockets)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2500,6 +4216,7 @@ impl McpServer {
             json!({
                 "name": "update_container_resources",
                 "description": "Update LXC container resources (cores, memory, swap, disk)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2517,6 +4234,7 @@ impl McpServer {
             json!({
                 "name": "add_disk",
                 "description": "Add a virtual disk to a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2535,6 +4253,7 @@ impl McpServer {
             json!({
                 "name": "remove_disk",
                 "description": "Remove (detach/delete) a virtual disk",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2549,6 +4268,7 @@ impl McpServer {
             json!({
                 "name": "add_network",
                 "description": "Add a network interface",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2567,6 +4287,7 @@ impl McpServer {
             json!({
                 "name": "remove_network",
                 "description": "Remove a network interface",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2581,6 +4302,7 @@ impl McpServer {
             json!({
                 "name": "set_vm_cloudinit",
                 "description": "Configure Cloud-Init settings for a VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2599,6 +4321,7 @@ impl McpServer {
             json!({
                 "name": "add_tag",
                 "description": "Add tags to a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2613,6 +4336,7 @@ impl McpServer {
             json!({
                 "name": "remove_tag",
                 "description": "Remove tags from a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2627,6 +4351,7 @@ impl McpServer {
             json!({
                 "name": "set_tags",
                 "description": "Set (overwrite) tags for a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2641,6 +4366,7 @@ impl McpServer {
             json!({
                 "name": "list_snapshots",
                 "description": "List snapshots for a VM or Container",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2649,11 +4375,13 @@ impl McpServer {
                         "type": { "type": "string", "enum": ["qemu", "lxc"] }
                     },
                     "required": ["node", "vmid"]
-                }
+                },
+                "outputSchema": output_schema("snapshots", list_of(SnapshotInfo::schema()))
             }),
             json!({
                 "name": "snapshot_vm",
                 "description": "Create a snapshot of a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2670,6 +4398,7 @@ impl McpServer {
             json!({
                 "name": "rollback_vm",
                 "description": "Rollback a VM or Container to a snapshot",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2684,6 +4413,7 @@ impl McpServer {
             json!({
                 "name": "delete_snapshot",
                 "description": "Delete a snapshot of a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2698,6 +4428,7 @@ impl McpServer {
             json!({
                 "name": "list_backups",
                 "description": "List backups on a specific storage",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2711,6 +4442,7 @@ impl McpServer {
             json!({
                 "name": "create_backup",
                 "description": "Create a backup (vzdump) of a VM or Container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2727,6 +4459,7 @@ impl McpServer {
             json!({
                 "name": "restore_backup",
                 "description": "Restore a VM or Container from a backup",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2748,6 +4481,7 @@ impl McpServer {
             json!({
                 "name": "list_templates",
                 "description": "List container templates on a storage",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2761,17 +4495,20 @@ impl McpServer {
             json!({
                 "name": "list_storage",
                 "description": "List all storage on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
                         "node": { "type": "string", "description": "The node name" }
                     },
                     "required": ["node"]
-                }
+                },
+                "outputSchema": output_schema("storage", list_of(StorageInfo::schema()))
             }),
             json!({
                 "name": "list_cluster_storage",
                 "description": "List all storage definitions in the cluster configuration",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -2781,6 +4518,7 @@ impl McpServer {
             json!({
                 "name": "list_isos",
                 "description": "List ISO images on a specific storage",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2793,6 +4531,7 @@ impl McpServer {
             json!({
                 "name": "add_storage",
                 "description": "Add a new storage definition",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2816,6 +4555,7 @@ impl McpServer {
             json!({
                 "name": "delete_storage",
                 "description": "Delete a storage definition",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2827,6 +4567,7 @@ impl McpServer {
             json!({
                 "name": "update_storage",
                 "description": "Update a storage definition",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2841,6 +4582,7 @@ impl McpServer {
             json!({
                 "name": "download_url",
                 "description": "Download an ISO or Container template from a URL to storage",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2863,6 +4605,7 @@ impl McpServer {
             json!({
                 "name": "list_networks",
                 "description": "List network interfaces and bridges on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2874,6 +4617,7 @@ impl McpServer {
             json!({
                 "name": "list_firewall_rules",
                 "description": "List firewall rules",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2886,6 +4630,7 @@ impl McpServer {
             json!({
                 "name": "add_firewall_rule",
                 "description": "Add a firewall rule",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2907,6 +4652,7 @@ impl McpServer {
             json!({
                 "name": "delete_firewall_rule",
                 "description": "Delete a firewall rule",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2925,6 +4671,7 @@ impl McpServer {
             json!({
                 "name": "list_tasks",
                 "description": "List recent tasks on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2932,11 +4679,13 @@ impl McpServer {
                         "limit": { "type": "integer", "description": "Max tasks to list (default: 50)" }
                     },
                     "required": ["node"]
-                }
+                },
+                "outputSchema": output_schema("tasks", list_of(TaskInfo::schema()))
             }),
             json!({
                 "name": "get_task_status",
                 "description": "Get the status of a specific task (UPID)",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2944,11 +4693,13 @@ impl McpServer {
                         "upid": { "type": "string", "description": "Unique Process ID" }
                     },
                     "required": ["node", "upid"]
-                }
+                },
+                "outputSchema": output_schema("task", TaskStatus::schema())
             }),
             json!({
                 "name": "read_task_log",
                 "description": "Read the log of a specific task (UPID)",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2961,6 +4712,7 @@ impl McpServer {
             json!({
                 "name": "wait_for_task",
                 "description": "Wait for a task to finish (with timeout)",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2969,11 +4721,13 @@ impl McpServer {
                         "timeout": { "type": "integer", "description": "Timeout in seconds (default: 60)" }
                     },
                     "required": ["node", "upid"]
-                }
+                },
+                "outputSchema": output_schema("task", TaskStatus::schema())
             }),
             json!({
                 "name": "list_services",
                 "description": "List system services on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2985,6 +4739,7 @@ impl McpServer {
             json!({
                 "name": "manage_service",
                 "description": "Manage a system service (Start, Stop, Restart, Reload)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -2998,6 +4753,7 @@ impl McpServer {
             json!({
                 "name": "list_apt_updates",
                 "description": "List available APT updates on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3009,6 +4765,7 @@ impl McpServer {
             json!({
                 "name": "run_apt_update",
                 "description": "Run apt-get update on a node (Async, returns UPID)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3020,6 +4777,7 @@ impl McpServer {
             json!({
                 "name": "get_apt_versions",
                 "description": "Get versions of installed Proxmox packages",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3031,6 +4789,7 @@ impl McpServer {
             json!({
                 "name": "get_subscription_info",
                 "description": "Get subscription status for a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3042,6 +4801,7 @@ impl McpServer {
             json!({
                 "name": "set_subscription_key",
                 "description": "Set a new subscription key",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3054,6 +4814,7 @@ impl McpServer {
             json!({
                 "name": "check_subscription",
                 "description": "Force update/check of the subscription",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3070,6 +4831,7 @@ impl McpServer {
             json!({
                 "name": "list_users",
                 "description": "List all users in the cluster",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3079,6 +4841,7 @@ impl McpServer {
             json!({
                 "name": "create_user",
                 "description": "Create a new user",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3098,6 +4861,7 @@ impl McpServer {
             json!({
                 "name": "delete_user",
                 "description": "Delete a user",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3109,6 +4873,7 @@ impl McpServer {
             json!({
                 "name": "list_roles",
                 "description": "List all defined roles and their privileges",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3118,6 +4883,7 @@ impl McpServer {
             json!({
                 "name": "create_role",
                 "description": "Create a new role with specific privileges",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3130,6 +4896,7 @@ impl McpServer {
             json!({
                 "name": "update_role",
                 "description": "Update role privileges",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3143,6 +4910,7 @@ impl McpServer {
             json!({
                 "name": "delete_role",
                 "description": "Delete a role",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3154,6 +4922,7 @@ impl McpServer {
             json!({
                 "name": "list_acls",
                 "description": "List all Access Control List (ACL) entries",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3163,6 +4932,7 @@ impl McpServer {
             json!({
                 "name": "update_acl",
                 "description": "Update Access Control List (Add/Remove permissions)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3185,6 +4955,7 @@ impl McpServer {
             json!({
                 "name": "list_pools",
                 "description": "List all resource pools",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3194,6 +4965,7 @@ impl McpServer {
             json!({
                 "name": "create_pool",
                 "description": "Create a new resource pool",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3206,6 +4978,7 @@ impl McpServer {
             json!({
                 "name": "get_pool_details",
                 "description": "Get detailed information about a resource pool",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3217,6 +4990,7 @@ impl McpServer {
             json!({
                 "name": "update_pool",
                 "description": "Update a resource pool (add/remove members or change comment)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3232,6 +5006,7 @@ impl McpServer {
             json!({
                 "name": "delete_pool",
                 "description": "Delete a resource pool",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3243,6 +5018,7 @@ impl McpServer {
             json!({
                 "name": "list_replication_jobs",
                 "description": "List all configured replication jobs",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3252,6 +5028,7 @@ impl McpServer {
             json!({
                 "name": "create_replication_job",
                 "description": "Create a new replication job",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3268,6 +5045,7 @@ impl McpServer {
             json!({
                 "name": "update_replication_job",
                 "description": "Update a replication job configuration",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3283,6 +5061,7 @@ impl McpServer {
             json!({
                 "name": "delete_replication_job",
                 "description": "Delete a replication job",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3294,6 +5073,7 @@ impl McpServer {
             json!({
                 "name": "list_ha_resources",
                 "description": "List all High Availability (HA) resources",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3303,6 +5083,7 @@ impl McpServer {
             json!({
                 "name": "list_ha_groups",
                 "description": "List all High Availability (HA) groups",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {},
@@ -3312,6 +5093,7 @@ impl McpServer {
             json!({
                 "name": "add_ha_resource",
                 "description": "Add a VM or Container to HA management",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3328,6 +5110,7 @@ impl McpServer {
             json!({
                 "name": "update_ha_resource",
                 "description": "Update HA resource configuration or state",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3344,6 +5127,7 @@ impl McpServer {
             json!({
                 "name": "remove_ha_resource",
                 "description": "Remove a resource from HA management",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3357,9 +5141,20 @@ impl McpServer {
 
     fn tool_defs_misc(&self) -> Vec<Value> {
         vec![
+            json!({
+                "name": "describe_policy",
+                "description": "Describe which tools this server permits (read-only mode and tool allow/deny policy)",
+                "annotations": { "readOnlyHint": true },
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }
+            }),
             json!({
                 "name": "vm_agent_ping",
                 "description": "Ping the QEMU Guest Agent inside a VM",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3372,6 +5167,7 @@ impl McpServer {
             json!({
                 "name": "vm_exec",
                 "description": "Execute a command inside a VM via QEMU Agent (Async, returns PID)",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3386,6 +5182,7 @@ impl McpServer {
             json!({
                 "name": "vm_exec_status",
                 "description": "Get status/output of a command executed via QEMU Agent",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3399,6 +5196,7 @@ impl McpServer {
             json!({
                 "name": "vm_read_file",
                 "description": "Read a file from inside a VM via QEMU Agent",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3412,6 +5210,7 @@ impl McpServer {
             json!({
                 "name": "vm_write_file",
                 "description": "Write to a file inside a VM via QEMU Agent",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3427,6 +5226,7 @@ impl McpServer {
             json!({
                 "name": "list_pci_devices",
                 "description": "List available PCI devices on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3438,6 +5238,7 @@ impl McpServer {
             json!({
                 "name": "list_usb_devices",
                 "description": "List available USB devices on a node",
+                "annotations": { "readOnlyHint": true },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3449,6 +5250,7 @@ impl McpServer {
             json!({
                 "name": "add_pci_device",
                 "description": "Add a PCI device to a VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3466,6 +5268,7 @@ impl McpServer {
             json!({
                 "name": "add_usb_device",
                 "description": "Add a USB device to a VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3482,6 +5285,7 @@ impl McpServer {
             json!({
                 "name": "remove_vm_device",
                 "description": "Remove a PCI or USB device from a VM",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3495,6 +5299,7 @@ impl McpServer {
             json!({
                 "name": "add_lxc_mountpoint",
                 "description": "Add a bind mount to an LXC container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -3513,6 +5318,7 @@ impl McpServer {
             json!({
                 "name": "remove_lxc_mountpoint",
                 "description": "Remove a mount point from an LXC container",
+                "annotations": { "readOnlyHint": false },
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -0,0 +1,89 @@
+use serde::Deserialize;
+
+/// Allow/deny lists over tool names from the `[policy]` config section.
+/// Patterns are globs where `*` matches any run of characters and `?` a
+/// single character, e.g. `delete_*` or `vm_*_file`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ToolPolicy {
+    /// Tools that may be used. Empty means every tool not denied.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Tools that may never be used. Takes precedence over `allow`.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    pub fn permits(&self, tool: &str) -> bool {
+        if self.deny.iter().any(|p| glob_match(p, tool)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|p| glob_match(p, tool))
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried against,
+    // so a mismatch can backtrack by letting the star absorb one more char.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("delete_*", "delete_vm"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("vm_*_file", "vm_write_file"));
+        assert!(glob_match("?tart_vm", "start_vm"));
+        assert!(glob_match("list_vms", "list_vms"));
+        assert!(!glob_match("delete_*", "undelete_vm"));
+        assert!(!glob_match("vm_*_file", "vm_exec"));
+        assert!(!glob_match("list_vms", "list_vms_extra"));
+    }
+
+    #[test]
+    fn test_policy_permits() {
+        let open = ToolPolicy::default();
+        assert!(open.permits("delete_vm"));
+
+        let policy = ToolPolicy {
+            allow: vec!["list_*".into(), "start_vm".into(), "stop_vm".into()],
+            deny: vec!["list_users".into()],
+        };
+        assert!(policy.permits("start_vm"));
+        assert!(policy.permits("list_vms"));
+        assert!(!policy.permits("delete_vm"));
+        // Deny wins over a matching allow
+        assert!(!policy.permits("list_users"));
+
+        let deny_only = ToolPolicy {
+            allow: vec![],
+            deny: vec!["vm_exec".into(), "vm_write_file".into()],
+        };
+        assert!(deny_only.permits("vm_read_file"));
+        assert!(!deny_only.permits("vm_exec"));
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::mcp::Notifier;
+use crate::proxmox::client::TaskStatus;
+use crate::proxmox::ProxmoxClient;
+
+/// Tools that start a long-running PVE task and, when the call carries a
+/// `progressToken`, follow it until it stops.
+pub const PROGRESS_TOOLS: &[&str] = &[
+    "create_backup",
+    "clone_vm",
+    "migrate_vm",
+    "restore_backup",
+    "download_url",
+];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Task log lines fetched per poll.
+const LOG_PAGE: u64 = 500;
+
+/// Sends `notifications/progress` for one tool call.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    token: Value,
+    notifier: Notifier,
+}
+
+impl ProgressReporter {
+    pub fn new(token: Value, notifier: Notifier) -> Self {
+        Self { token, notifier }
+    }
+
+    pub fn tracks(tool: &str) -> bool {
+        PROGRESS_TOOLS.contains(&tool)
+    }
+
+    pub fn report(&self, progress: f64, message: &str) {
+        let _ = self.notifier.send(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": {
+                "progressToken": self.token,
+                "progress": progress,
+                "total": 100,
+                "message": message
+            }
+        }));
+    }
+
+    /// Polls the task until it stops, reporting every new percentage found
+    /// in its log, and returns the final task status.
+    pub async fn follow_task(&self, client: &ProxmoxClient, upid: &str) -> Result<TaskStatus> {
+        // UPID:<node>:<pid>:<pstart>:<starttime>:<type>:<id>:<user>:
+        let node = upid
+            .split(':')
+            .nth(1)
+            .filter(|n| !n.is_empty())
+            .ok_or(anyhow::anyhow!("Malformed UPID: {}", upid))?;
+
+        let mut read = 0;
+        // Progress must only increase, so restarts (e.g. the next disk of a
+        // backup) are not reported.
+        let mut last = 0.0;
+        loop {
+            let status = client.get_task_status(node, upid).await?;
+            let lines = client.get_task_log_page(node, upid, read, LOG_PAGE).await?;
+            for line in &lines {
+                let text = line["t"].as_str().unwrap_or_default();
+                if let Some(pct) = parse_percent(text).filter(|p| *p > last) {
+                    self.report(pct, text.trim());
+                    last = pct;
+                }
+                read = read.max(line["n"].as_u64().unwrap_or(read));
+            }
+
+            if status.is_stopped() {
+                if lines.len() as u64 == LOG_PAGE {
+                    // Drain the rest of the log before finishing
+                    continue;
+                }
+                if status.exitstatus.as_deref() == Some("OK") && last < 100.0 {
+                    self.report(100.0, "Task finished");
+                }
+                return Ok(status);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Extracts the first percentage from a task log line, as printed by
+/// vzdump (`INFO: 45% (...)`), qmrestore (`progress 45% (...)`), drive
+/// mirroring during clone and migrate (`transferred ... (3.12%)`) and
+/// qemu-img (`(12.00/100%)`).
+pub fn parse_percent(line: &str) -> Option<f64> {
+    line.match_indices('%').find_map(|(i, _)| {
+        let head = &line[..i];
+        let head = head.strip_suffix("/100").unwrap_or(head);
+        let digits = &head[head
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+            .len()..];
+        digits
+            .parse::<f64>()
+            .ok()
+            .filter(|p| (0.0..=100.0).contains(p))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_percent() {
+        let cases = [
+            (
+                "INFO:  45% (14.4 GiB of 32.0 GiB) in 1m 2s, read: 237.6 MiB/s",
+                Some(45.0),
+            ),
+            (
+                "progress 7% (read 751619276 bytes, duration 3 sec)",
+                Some(7.0),
+            ),
+            (
+                "drive-scsi0: transferred 1.0 GiB of 32.0 GiB (3.13%) in 5s",
+                Some(3.13),
+            ),
+            ("    (12.00/100%)", Some(12.0)),
+            ("INFO: status = running", None),
+            ("set ratio to 150%", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_percent(line), expected, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::mcp::McpError;
+
+/// A parameterized runbook served through `prompts/get`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Prompt {
+    /// Defaults to the file stem for prompts loaded from a directory.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+    /// Tools the runbook relies on. The prompt is only offered while all of
+    /// them may be called.
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Message text; `{arg}` is replaced by the argument's value.
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// Used when an optional argument is not given.
+    #[serde(default, skip_serializing)]
+    pub default: Option<String>,
+}
+
+impl Prompt {
+    fn builtin(
+        name: &str,
+        description: &str,
+        arguments: &[(&str, &str, Option<&str>)],
+        tools: &[&str],
+        template: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            arguments: arguments
+                .iter()
+                .map(|(name, description, default)| PromptArgument {
+                    name: name.to_string(),
+                    description: Some(description.to_string()),
+                    required: default.is_none(),
+                    default: default.map(str::to_string),
+                })
+                .collect(),
+            tools: tools.iter().map(|t| t.to_string()).collect(),
+            template: template.to_string(),
+        }
+    }
+
+    /// The `prompts/list` entry for this prompt.
+    pub fn definition(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "arguments": self.arguments
+        })
+    }
+
+    /// Fills in the template. Missing required arguments are an invalid
+    /// params error.
+    pub fn render(&self, args: &Map<String, Value>) -> Result<String> {
+        let mut values = BTreeMap::new();
+        for arg in &self.arguments {
+            let value = match args.get(&arg.name) {
+                Some(Value::String(s)) => s.clone(),
+                Some(v) if !v.is_null() => v.to_string(),
+                _ => match &arg.default {
+                    Some(default) => default.clone(),
+                    None if arg.required => {
+                        return Err(McpError::InvalidParams(format!(
+                            "Missing required argument '{}' for prompt '{}'",
+                            arg.name, self.name
+                        ))
+                        .into())
+                    }
+                    None => String::new(),
+                },
+            };
+            values.insert(arg.name.as_str(), value);
+        }
+
+        // One pass over the template, so that values are never substituted
+        // into themselves
+        let mut text = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let token = &rest[start..];
+            match token[1..]
+                .find('}')
+                .and_then(|end| Some((values.get(&token[1..end + 1])?, end + 2)))
+            {
+                Some((value, len)) => {
+                    text.push_str(value);
+                    rest = &token[len..];
+                }
+                None => {
+                    text.push('{');
+                    rest = &token[1..];
+                }
+            }
+        }
+        text.push_str(rest);
+        Ok(text)
+    }
+}
+
+fn builtin_prompts() -> Vec<Prompt> {
+    vec![
+        Prompt::builtin(
+            "diagnose_vm_start",
+            "Find out why a VM or container fails to start",
+            &[("vmid", "ID of the guest that won't start", None)],
+            &["get_vm_config", "list_tasks", "read_task_log"],
+            "Guest {vmid} won't start. Diagnose why:\n\
+             1. Read its configuration with `get_vm_config` and note the node, disks, \
+             passthrough devices and memory it needs.\n\
+             2. Use `list_tasks` on that node to find the most recent start tasks \
+             (qmstart/vzstart) for {vmid}.\n\
+             3. Read the failed tasks with `read_task_log` and quote the error lines.\n\
+             Explain the most likely cause and propose a fix, but do not change \
+             anything without asking.",
+        ),
+        Prompt::builtin(
+            "prepare_node_maintenance",
+            "Plan and carry out evacuating a node before maintenance",
+            &[
+                ("node", "Node that goes into maintenance", None),
+                (
+                    "target_node",
+                    "Node to move guests to",
+                    Some("the other online nodes, balancing their load"),
+                ),
+            ],
+            &["list_vms", "list_ha_resources", "migrate_vm"],
+            "Prepare node {node} for maintenance.\n\
+             1. Use `list_vms` to find every guest running on {node}.\n\
+             2. Check `list_ha_resources` for guests managed by HA, since HA may move \
+             or restart them on its own.\n\
+             3. Present a migration plan that moves the running guests to \
+             {target_node}, flagging guests with local disks or passthrough devices \
+             that cannot be migrated online.\n\
+             4. Once I approve the plan, migrate the guests one at a time with \
+             `migrate_vm` and confirm each migration finished before the next.",
+        ),
+        Prompt::builtin(
+            "audit_old_snapshots",
+            "List snapshots older than a given age",
+            &[(
+                "days",
+                "Report snapshots older than this many days",
+                Some("30"),
+            )],
+            &["list_vms", "list_snapshots"],
+            "Audit the snapshots in the cluster.\n\
+             1. Use `list_vms` to enumerate all guests.\n\
+             2. For each guest, call `list_snapshots` and keep the snapshots whose \
+             snaptime is more than {days} days ago (ignore the `current` entry).\n\
+             3. Report a table of guest, snapshot name, description and age in days, \
+             oldest first, with a total count per guest.\n\
+             Do not delete anything; only recommend which snapshots look safe to remove.",
+        ),
+        Prompt::builtin(
+            "review_firewall_rules",
+            "Review the firewall rules of a guest",
+            &[("vmid", "ID of the guest whose rules to review", None)],
+            &["get_vm_config", "list_firewall_rules"],
+            "Review the firewall of guest {vmid}.\n\
+             1. Read its configuration with `get_vm_config` to learn its node, \
+             network interfaces and whether `firewall=1` is set on them.\n\
+             2. List its rules with `list_firewall_rules` (node and vmid), and the \
+             node and cluster rules that also apply.\n\
+             3. Point out rules that are overly broad (any source, any port), \
+             shadowed or duplicated, disabled, or missing comments, and interfaces \
+             with the firewall turned off.\n\
+             Suggest concrete rule changes but do not apply them.",
+        ),
+    ]
+}
+
+/// Built-in prompts plus those an operator put in the prompts directory.
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    prompts: BTreeMap<String, Prompt>,
+}
+
+impl Default for PromptLibrary {
+    fn default() -> Self {
+        Self {
+            prompts: builtin_prompts()
+                .into_iter()
+                .map(|p| (p.name.clone(), p))
+                .collect(),
+        }
+    }
+}
+
+impl PromptLibrary {
+    /// Adds every `.toml`, `.yaml`/`.yml` and `.json` file in `dir` as a
+    /// prompt, replacing a built-in prompt of the same name.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize> {
+        let mut paths = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read prompts directory {}", dir.display()))?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+
+        let mut loaded = 0;
+        for path in paths {
+            let is_prompt = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| matches!(e, "toml" | "yaml" | "yml" | "json"));
+            if !is_prompt || !path.is_file() {
+                continue;
+            }
+            let mut prompt: Prompt = config::Config::builder()
+                .add_source(config::File::from(path.as_path()))
+                .build()
+                .and_then(|c| c.try_deserialize())
+                .with_context(|| format!("Invalid prompt file {}", path.display()))?;
+            if prompt.name.is_empty() {
+                prompt.name = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            self.prompts.insert(prompt.name.clone(), prompt);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Prompt> {
+        self.prompts.values()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prompt> {
+        self.prompts.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let library = PromptLibrary::default();
+
+        let prompt = library.get("diagnose_vm_start").unwrap();
+        let text = prompt.render(&args(json!({ "vmid": 100 }))).unwrap();
+        assert!(text.starts_with("Guest 100 won't start."));
+        assert!(!text.contains("{vmid}"));
+        assert!(prompt.render(&Map::new()).is_err());
+
+        // Optional arguments fall back to their default
+        let prompt = library.get("audit_old_snapshots").unwrap();
+        assert!(prompt.render(&Map::new()).unwrap().contains("30 days"));
+        let text = prompt.render(&args(json!({ "days": "7" }))).unwrap();
+        assert!(text.contains("7 days"));
+
+        // Values are inserted verbatim, never rendered themselves
+        let arg = |name: &str| PromptArgument {
+            name: name.into(),
+            description: None,
+            required: true,
+            default: None,
+        };
+        let prompt = Prompt {
+            name: "p".into(),
+            description: None,
+            arguments: vec![arg("a"), arg("b")],
+            template: "{a} then {b} {unknown} {".into(),
+            tools: vec![],
+        };
+        let text = prompt
+            .render(&args(json!({ "a": "{b}", "b": "x" })))
+            .unwrap();
+        assert_eq!(text, "{b} then x {unknown} {");
+    }
+
+    #[test]
+    fn test_builtin_arguments() {
+        for prompt in PromptLibrary::default().iter() {
+            for arg in &prompt.arguments {
+                assert!(
+                    prompt.template.contains(&format!("{{{}}}", arg.name)),
+                    "{} does not use {}",
+                    prompt.name,
+                    arg.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::policy::glob_match;
+
+/// Which GET responses are reused and for how long, from the `[cache]`
+/// config section.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Lifetime in milliseconds per API path pattern (`*` matches any run
+    /// of characters). Paths matching no pattern are not cached; the
+    /// first matching pattern in sort order wins.
+    pub ttl_ms: BTreeMap<String, u64>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_ms: BTreeMap::from([
+                ("cluster/resources".to_string(), 2_000),
+                ("cluster/status".to_string(), 5_000),
+                ("nodes".to_string(), 5_000),
+                ("nodes/*/storage".to_string(), 10_000),
+                ("version".to_string(), 60_000),
+            ]),
+        }
+    }
+}
+
+/// Short-lived copies of the `data` of GET responses, keyed by path and
+/// query, shared by every clone of a client.
+pub(crate) struct ResponseCache {
+    settings: CacheSettings,
+    entries: DashMap<String, (Instant, Value)>,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(settings: CacheSettings) -> Self {
+        Self {
+            settings,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// How long a GET of `path` may be reused, if it is cached at all.
+    pub(crate) fn ttl(&self, path: &str) -> Option<Duration> {
+        if !self.settings.enabled {
+            return None;
+        }
+        let path = path.split('?').next().unwrap_or_default();
+        self.settings
+            .ttl_ms
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, path))
+            .map(|(_, ttl)| Duration::from_millis(*ttl))
+    }
+
+    pub(crate) fn get(&self, path: &str) -> Option<Value> {
+        let entry = self.entries.get(path)?;
+        (entry.0 > Instant::now()).then(|| entry.1.clone())
+    }
+
+    pub(crate) fn insert(&self, path: &str, value: Value, ttl: Duration) {
+        self.entries
+            .insert(path.to_string(), (Instant::now() + ttl, value));
+    }
+
+    /// Drops the responses a mutating request to `path` may have changed:
+    /// `cluster/resources`, the listings `path` is part of, and everything
+    /// below the guest or object it addresses (e.g. `nodes/pve1/qemu/100`
+    /// for `nodes/pve1/qemu/100/status/start`, `nodes/pve1` for
+    /// `nodes/pve1/network`).
+    pub(crate) fn invalidate(&self, path: &str) {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').collect();
+        let owner = match segments.as_slice() {
+            ["nodes", _, "qemu" | "lxc", _, ..] => 4,
+            _ => 2,
+        };
+        let owner = segments[..owner.min(segments.len())].join("/");
+        let within = |parent: &str, child: &str| {
+            child == parent || child.starts_with(&format!("{}/", parent))
+        };
+
+        self.entries.retain(|key, _| {
+            let cached = key.split('?').next().unwrap_or_default();
+            !(cached == "cluster/resources" || within(cached, path) || within(&owner, cached))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cache_with(paths: &[&str]) -> ResponseCache {
+        let cache = ResponseCache::new(CacheSettings::default());
+        for path in paths {
+            cache.insert(path, json!(path), Duration::from_secs(60));
+        }
+        cache
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = ResponseCache::new(CacheSettings::default());
+        assert_eq!(cache.ttl("cluster/resources"), Some(Duration::from_secs(2)));
+        assert_eq!(
+            cache.ttl("nodes/pve1/storage?content=images"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(cache.ttl("nodes/pve1/qemu"), None);
+
+        let disabled = ResponseCache::new(CacheSettings {
+            enabled: false,
+            ..CacheSettings::default()
+        });
+        assert_eq!(disabled.ttl("cluster/resources"), None);
+
+        cache.insert("nodes", json!([]), Duration::ZERO);
+        assert!(cache.get("nodes").is_none());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = cache_with(&[
+            "cluster/resources",
+            "nodes",
+            "nodes/pve1/qemu",
+            "nodes/pve1/qemu/100/config",
+            "nodes/pve1/qemu/101/config",
+            "nodes/pve1/storage",
+            "nodes/pve2/storage",
+        ]);
+        cache.invalidate("nodes/pve1/qemu/100/status/start");
+        assert!(cache.get("cluster/resources").is_none());
+        assert!(cache.get("nodes").is_none());
+        assert!(cache.get("nodes/pve1/qemu").is_none());
+        assert!(cache.get("nodes/pve1/qemu/100/config").is_none());
+        assert!(cache.get("nodes/pve1/qemu/101/config").is_some());
+        assert!(cache.get("nodes/pve1/storage").is_some());
+
+        cache.invalidate("nodes/pve1/storage/local/upload");
+        assert!(cache.get("nodes/pve1/storage").is_none());
+        assert!(cache.get("nodes/pve2/storage").is_some());
+    }
+}
@@ -1,18 +1,67 @@
+use crate::proxmox::cache::{CacheSettings, ResponseCache};
+use crate::proxmox::endpoints::{EndpointHealth, EndpointPool};
 use crate::proxmox::error::{ProxmoxError, Result as PveResult};
+use crate::proxmox::limits::{RateLimit, RateLimiter};
+use crate::proxmox::metrics::{
+    ApiMetrics, CACHE_HITS, QUEUED, QUEUE_TIMEOUTS, REQUESTS, RETRIES, RETRIES_EXHAUSTED,
+};
+use crate::proxmox::retry::{is_transient, is_unavailable, RetryPolicy};
 use anyhow::{Context, Result};
-use log::info;
-use reqwest::{Client, Method};
+use log::{debug, info, warn};
+use reqwest::{Client, Method, StatusCode};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Map, Value};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use url::Url;
 
+/// PVE tickets are valid for two hours; renew well before that so that a
+/// request never races the expiry.
+const TICKET_RENEW_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Keeps a rebooting node from stalling requests before failover kicks in.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Clone)]
 pub struct ProxmoxClient {
     pub(crate) client: Client,
     pub(crate) base_url: Url,
-    ticket: Option<String>,
-    csrf_token: Option<String>,
+    endpoints: Arc<EndpointPool>,
+    auth: Arc<TicketAuth>,
     api_token: Option<String>,
+    dry_run: Option<Arc<Mutex<Vec<PlannedRequest>>>>,
+    retry: Arc<RetryPolicy>,
+    limiter: Arc<RateLimiter>,
+    cache: Arc<ResponseCache>,
+    bypass_cache: bool,
+    metrics: Arc<ApiMetrics>,
+}
+
+/// A mutating request that dry-run mode recorded instead of sending.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// Ticket-based session shared by every clone of a client, so that all SSE
+/// sessions see a renewal as soon as one of them performs it.
+struct TicketAuth {
+    session: RwLock<Option<TicketSession>>,
+    credentials: RwLock<Option<(String, String)>>,
+    // Serializes logins so concurrent 401s trigger a single re-authentication.
+    login_lock: tokio::sync::Mutex<()>,
+    renew_after: RwLock<Duration>,
+}
+
+#[derive(Clone)]
+struct TicketSession {
+    ticket: String,
+    csrf_token: String,
+    issued_at: Instant,
+    generation: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -35,6 +84,220 @@ pub struct VmInfo {
     pub node: Option<String>,
     #[serde(rename = "type")]
     pub vm_type: Option<String>,
+    pub pool: Option<String>,
+    pub tags: Option<String>,
+}
+
+impl VmInfo {
+    /// JSON Schema of the serialized struct, for tool output schemas.
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "vmid": { "type": "integer" },
+                "name": { "type": ["string", "null"] },
+                "status": { "type": "string", "description": "running, stopped, ..." },
+                "node": { "type": ["string", "null"] },
+                "type": { "type": ["string", "null"], "enum": ["qemu", "lxc", null] },
+                "pool": { "type": ["string", "null"] },
+                "tags": { "type": ["string", "null"], "description": "Semicolon separated tags" }
+            },
+            "required": ["vmid", "status"]
+        })
+    }
+}
+
+/// A node as listed by `GET /nodes`. Fields not modelled here are kept in
+/// `extra`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NodeInfo {
+    pub node: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxcpu: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxmem: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl NodeInfo {
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "node": { "type": "string" },
+                "status": { "type": "string", "enum": ["online", "offline", "unknown"] },
+                "cpu": { "type": "number", "description": "CPU utilization (0-1)" },
+                "maxcpu": { "type": "integer" },
+                "mem": { "type": "integer", "description": "Used memory in bytes" },
+                "maxmem": { "type": "integer" },
+                "uptime": { "type": "integer", "description": "Seconds" }
+            },
+            "required": ["node"]
+        })
+    }
+}
+
+/// Status of a task as returned by `nodes/{node}/tasks/{upid}/status`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskStatus {
+    /// `running` or `stopped`.
+    pub status: String,
+    /// `OK` or the error, once the task has stopped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exitstatus: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starttime: Option<i64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl TaskStatus {
+    pub fn is_stopped(&self) -> bool {
+        self.status == "stopped"
+    }
+
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["running", "stopped"] },
+                "exitstatus": { "type": "string", "description": "OK, or the error of a failed task" },
+                "upid": { "type": "string" },
+                "node": { "type": "string" },
+                "type": { "type": "string", "description": "Task type, e.g. qmstart or vzdump" },
+                "id": { "type": "string", "description": "Object the task works on, usually a VMID" },
+                "user": { "type": "string" },
+                "starttime": { "type": "integer", "description": "Unix timestamp" }
+            },
+            "required": ["status"]
+        })
+    }
+}
+
+/// An entry of `nodes/{node}/tasks`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskInfo {
+    pub upid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Exit status of a finished task; absent while it is running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starttime: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endtime: Option<i64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl TaskInfo {
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "upid": { "type": "string" },
+                "node": { "type": "string" },
+                "type": { "type": "string" },
+                "id": { "type": "string" },
+                "user": { "type": "string" },
+                "status": { "type": "string", "description": "Exit status; absent while running" },
+                "starttime": { "type": "integer" },
+                "endtime": { "type": "integer" }
+            },
+            "required": ["upid"]
+        })
+    }
+}
+
+/// An entry of a guest's snapshot list. The list always ends with the
+/// pseudo-snapshot `current`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snaptime: Option<i64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl SnapshotInfo {
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "parent": { "type": "string" },
+                "snaptime": { "type": "integer", "description": "Unix timestamp; absent for current" }
+            },
+            "required": ["name"]
+        })
+    }
+}
+
+/// A storage as seen by a node (`nodes/{node}/storage`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StorageInfo {
+    pub storage: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub storage_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avail: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl StorageInfo {
+    pub fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "storage": { "type": "string" },
+                "type": { "type": "string", "description": "dir, lvmthin, zfspool, nfs, ..." },
+                "content": { "type": "string", "description": "Comma separated content types" },
+                "total": { "type": "integer", "description": "Bytes" },
+                "used": { "type": "integer" },
+                "avail": { "type": "integer" }
+            },
+            "required": ["storage"]
+        })
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -45,10 +308,45 @@ pub struct ClusterResource {
     pub res_type: String,
     pub status: Option<String>,
     pub name: Option<String>,
+    pub pool: Option<String>,
+    pub tags: Option<String>,
+    pub storage: Option<String>,
+    pub disk: Option<u64>,
+    pub maxdisk: Option<u64>,
 }
 
 impl ProxmoxClient {
     pub fn new(host: &str, port: u16, verify_ssl: bool) -> Result<Self> {
+        let base_url = Self::api_url(host, port)?;
+
+        let client = Client::builder()
+            .danger_accept_invalid_certs(!verify_ssl)
+            .cookie_store(true)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .context("Failed to build reqwest client")?;
+
+        Ok(Self {
+            client,
+            endpoints: Arc::new(EndpointPool::new(base_url.clone())),
+            base_url,
+            auth: Arc::new(TicketAuth {
+                session: RwLock::new(None),
+                credentials: RwLock::new(None),
+                login_lock: tokio::sync::Mutex::new(()),
+                renew_after: RwLock::new(TICKET_RENEW_AFTER),
+            }),
+            api_token: None,
+            dry_run: None,
+            retry: Arc::new(RetryPolicy::default()),
+            limiter: Arc::new(RateLimiter::new(RateLimit::default())),
+            cache: Arc::new(ResponseCache::new(CacheSettings::default())),
+            bypass_cache: false,
+            metrics: Arc::new(ApiMetrics::default()),
+        })
+    }
+
+    fn api_url(host: &str, port: u16) -> Result<Url> {
         let scheme = if host.starts_with("http://") {
             "http"
         } else {
@@ -66,21 +364,99 @@ impl ProxmoxClient {
 
         let url_str = format!("{}://{}:{}/api2/json/", scheme, host_cleaned, port);
 
-        let base_url = Url::parse(&url_str).context("Invalid host URL")?;
+        Url::parse(&url_str).context("Invalid host URL")
+    }
 
-        let client = Client::builder()
-            .danger_accept_invalid_certs(!verify_ssl)
-            .cookie_store(true)
-            .build()
-            .context("Failed to build reqwest client")?;
+    /// Adds another API host (`host[:port]`) of the same cluster to fail
+    /// over to when the primary host is unreachable. The primary's scheme
+    /// and port are used unless the host names its own.
+    pub fn add_failover_host(&self, host: &str) -> Result<()> {
+        let host = if host.contains("://") {
+            host.to_string()
+        } else {
+            format!("{}://{}", self.base_url.scheme(), host)
+        };
+        let parsed = Url::parse(&host).context("Invalid failover host")?;
+        let host_str = parsed
+            .host_str()
+            .ok_or(anyhow::anyhow!("Invalid failover host: {}", host))?;
+        let port = parsed.port().or(self.base_url.port()).unwrap_or(8006);
+        let host = format!("{}://{}", parsed.scheme(), host_str);
+        self.endpoints.add(Self::api_url(&host, port)?);
+        Ok(())
+    }
 
-        Ok(Self {
-            client,
-            base_url,
-            ticket: None,
-            csrf_token: None,
-            api_token: None,
-        })
+    /// Adds the addresses of all cluster nodes reported by `cluster/status`
+    /// as failover hosts. Returns the number of newly added endpoints.
+    pub async fn discover_endpoints(&self) -> Result<usize> {
+        let port = self.base_url.port().unwrap_or(8006);
+        let mut added = 0;
+        for entry in self.get_cluster_status().await? {
+            if entry.get("type").and_then(|v| v.as_str()) != Some("node") {
+                continue;
+            }
+            let Some(ip) = entry.get("ip").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let host = format!("{}://{}", self.base_url.scheme(), ip);
+            if self.endpoints.add(Self::api_url(&host, port)?) {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.health()
+    }
+
+    /// Whether the client has a password but no ticket yet, because logging
+    /// in has failed so far. The next request tries again.
+    pub fn login_pending(&self) -> bool {
+        self.api_token.is_none() && self.credentials().is_some() && self.current_session().is_none()
+    }
+
+    /// Returns a clone of this client that records mutating requests
+    /// instead of sending them. GETs still reach PVE so that handlers can
+    /// compute their requests from the current state.
+    pub fn with_dry_run(&self) -> Self {
+        Self {
+            dry_run: Some(Arc::new(Mutex::new(Vec::new()))),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a clone of this client whose GETs always reach PVE. Their
+    /// responses still refresh the cache.
+    pub fn without_cache(&self) -> Self {
+        Self {
+            bypass_cache: true,
+            ..self.clone()
+        }
+    }
+
+    /// The requests recorded by a dry-run client, in order.
+    pub fn planned_requests(&self) -> Vec<PlannedRequest> {
+        self.dry_run
+            .as_ref()
+            .map(|log| log.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = Arc::new(policy);
+    }
+
+    pub fn set_rate_limit(&mut self, limit: RateLimit) {
+        self.limiter = Arc::new(RateLimiter::new(limit));
+    }
+
+    pub fn set_cache(&mut self, settings: CacheSettings) {
+        self.cache = Arc::new(ResponseCache::new(settings));
+    }
+
+    pub fn metrics(&self) -> &ApiMetrics {
+        &self.metrics
     }
 
     pub fn set_api_token(&mut self, user: &str, token_name: &str, token_value: &str) {
@@ -91,72 +467,332 @@ impl ProxmoxClient {
     }
 
     pub async fn login(&mut self, user: &str, password: &str) -> Result<()> {
-        let url = self.base_url.join("access/ticket")?;
+        *self.auth.credentials.write().unwrap() = Some((user.to_string(), password.to_string()));
+        let _guard = self.auth.login_lock.lock().await;
+        self.fetch_ticket(user, password).await?;
+
+        info!("Successfully logged in as {}", user);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_ticket_renewal(&self, renew_after: Duration) {
+        *self.auth.renew_after.write().unwrap() = renew_after;
+    }
+
+    /// Requests a ticket from `access/ticket`. The password may also be a
+    /// still-valid ticket, which PVE treats as a renewal. Callers must hold
+    /// `login_lock`.
+    async fn fetch_ticket(&self, user: &str, password: &str) -> PveResult<()> {
         let params = [("username", user), ("password", password)];
 
         let resp = self
-            .client
-            .post(url)
-            .form(&params)
-            .send()
-            .await
-            .context("Login request failed")?;
+            .execute(&Method::POST, "access/ticket", |url| {
+                self.client.post(url).form(&params)
+            })
+            .await?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let text = resp.text().await.unwrap_or_default();
-            return Err(ProxmoxError::Auth(format!("{} - {}", status, text)).into());
+            return Err(ProxmoxError::Auth(format!("{} - {}", status, text)));
         }
 
-        let body: TicketResponse = resp
-            .json()
-            .await
-            .context("Failed to parse login response")?;
+        let body: TicketResponse = resp.json().await?;
 
-        self.ticket = Some(body.data.ticket);
-        self.csrf_token = Some(body.data.csrf_token);
+        let mut session = self.auth.session.write().unwrap();
+        let generation = session.as_ref().map(|s| s.generation + 1).unwrap_or(0);
+        *session = Some(TicketSession {
+            ticket: body.data.ticket,
+            csrf_token: body.data.csrf_token,
+            issued_at: Instant::now(),
+            generation,
+        });
+        Ok(())
+    }
 
-        info!("Successfully logged in as {}", user);
+    fn current_session(&self) -> Option<TicketSession> {
+        self.auth.session.read().unwrap().clone()
+    }
+
+    fn credentials(&self) -> Option<(String, String)> {
+        self.auth.credentials.read().unwrap().clone()
+    }
+
+    /// Renews the ticket proactively once it is older than the renewal
+    /// threshold. Falls back to a full login if the renewal is rejected, or
+    /// if there is no ticket yet because the login at startup failed.
+    async fn ensure_fresh_ticket(&self) -> PveResult<()> {
+        let renew_after = *self.auth.renew_after.read().unwrap();
+        let stale = |s: &Option<TicketSession>| {
+            s.as_ref()
+                .is_none_or(|s| s.issued_at.elapsed() >= renew_after)
+        };
+        if !stale(&self.current_session()) {
+            return Ok(());
+        }
+        let Some((user, password)) = self.credentials() else {
+            return Ok(());
+        };
+
+        let _guard = self.auth.login_lock.lock().await;
+        // Another caller may have renewed while we were waiting for the lock.
+        let session = self.current_session();
+        if !stale(&session) {
+            return Ok(());
+        }
+
+        let Some(session) = session else {
+            info!("Logging in to PVE as {}", user);
+            return self.fetch_ticket(&user, &password).await;
+        };
+        debug!("Renewing PVE ticket for {}", user);
+        if let Err(e) = self.fetch_ticket(&user, &session.ticket).await {
+            warn!("Ticket renewal failed ({}), logging in again", e);
+            self.fetch_ticket(&user, &password).await?;
+        }
         Ok(())
     }
 
+    /// Logs in again after a 401, unless another caller already replaced
+    /// the ticket that was rejected.
+    async fn reauthenticate(&self, rejected_generation: Option<u64>) -> PveResult<()> {
+        let Some((user, password)) = self.credentials() else {
+            return Ok(());
+        };
+
+        let _guard = self.auth.login_lock.lock().await;
+        let current = self.current_session().map(|s| s.generation);
+        if current != rejected_generation {
+            return Ok(());
+        }
+
+        info!("PVE ticket rejected, logging in again as {}", user);
+        self.fetch_ticket(&user, &password).await
+    }
+
     pub(crate) async fn request<T: serde::de::DeserializeOwned>(
         &self,
         method: Method,
         path: &str,
         body: Option<&Value>,
     ) -> PveResult<T> {
-        let url = self.base_url.join(path).map_err(ProxmoxError::Url)?;
-        let mut req = self.client.request(method, url);
+        if let Some(log) = self.dry_run.as_ref().filter(|_| method != Method::GET) {
+            log.lock().unwrap().push(PlannedRequest {
+                method: method.to_string(),
+                path: self.base_url.join(path)?[url::Position::BeforePath..].to_string(),
+                body: body.cloned(),
+            });
+            // Callers expect a UPID string, `()` or a JSON value back.
+            return serde_json::from_value(Value::Null)
+                .or_else(|_| serde_json::from_value(Value::String("dry-run".to_string())))
+                .map_err(ProxmoxError::Json);
+        }
 
-        if let Some(token) = &self.api_token {
-            req = req.header("Authorization", token);
-        } else {
-            if let Some(token) = &self.csrf_token {
-                req = req.header("CSRFPreventionToken", token);
-            }
-            if let Some(ticket) = &self.ticket {
-                req = req.header("Cookie", format!("PVEAuthCookie={}", ticket));
-            }
+        let ttl = self.cache.ttl(path).filter(|_| method == Method::GET);
+        if let Some(data) = ttl
+            .filter(|_| !self.bypass_cache)
+            .and_then(|_| self.cache.get(path))
+        {
+            debug!("PVE API request: {} {} (cached)", method, path);
+            self.metrics.incr(CACHE_HITS, &method);
+            return serde_json::from_value(data).map_err(ProxmoxError::Json);
         }
 
-        if let Some(b) = body {
-            req = req.json(b);
+        let data = self.fetch(&method, path, body).await;
+        if method != Method::GET {
+            // Even a failed call may have changed something
+            self.cache.invalidate(path);
+        }
+        let data = data?;
+        if let Some(ttl) = ttl {
+            self.cache.insert(path, data.clone(), ttl);
         }
+        serde_json::from_value(data).map_err(ProxmoxError::Json)
+    }
 
-        let resp = req.send().await.map_err(ProxmoxError::Request)?;
+    /// Sends a request, retrying transient failures, and returns the
+    /// `data` of the response.
+    async fn fetch(&self, method: &Method, path: &str, body: Option<&Value>) -> PveResult<Value> {
+        if self.api_token.is_none() {
+            self.ensure_fresh_ticket().await?;
+        }
 
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let text = resp.text().await.unwrap_or_default();
-            return Err(ProxmoxError::Api(status, text));
+        let max_retries = self.retry.max_retries(method);
+        let mut retries = 0;
+        let resp = loop {
+            debug!("PVE API request: {} {}", method, path);
+            // The delay before the next attempt if the failure is worth retrying
+            let (delay, error) = match self.send_authenticated(method, path, body).await {
+                Ok(resp) if resp.status().is_success() => break resp,
+                Ok(resp) => {
+                    let status = resp.status();
+                    let retry_after = resp
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse().ok())
+                        .map(Duration::from_secs);
+                    let text = resp.text().await.unwrap_or_default();
+                    debug!(
+                        "PVE API request {} {} failed: {} {}",
+                        method,
+                        path,
+                        status,
+                        text.trim()
+                    );
+                    let delay = (*method == Method::GET && is_transient(status, &text))
+                        .then(|| retry_after.unwrap_or_else(|| self.retry.backoff(retries)));
+                    (delay, ProxmoxError::from_response(status, text))
+                }
+                // A connection that could not be established never carried
+                // the request, so even a mutating call can be sent again.
+                Err(ProxmoxError::Request(e))
+                    if e.is_connect() || (*method == Method::GET && e.is_timeout()) =>
+                {
+                    (Some(self.retry.backoff(retries)), ProxmoxError::Request(e))
+                }
+                Err(e) => return Err(e),
+            };
+
+            match delay {
+                Some(delay) if retries < max_retries && delay <= self.retry.max_delay() => {
+                    retries += 1;
+                    self.metrics.incr(RETRIES, method);
+                    warn!(
+                        "Retrying {} {} in {}ms (retry {}/{}): {}",
+                        method,
+                        path,
+                        delay.as_millis(),
+                        retries,
+                        max_retries,
+                        error
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Some(_) if max_retries > 0 => {
+                    self.metrics.incr(RETRIES_EXHAUSTED, method);
+                    warn!(
+                        "Giving up on {} {} after {} retries: {}",
+                        method, path, retries, error
+                    );
+                    return Err(error);
+                }
+                _ => return Err(error),
+            }
+        };
+
+        let mut v: Value = resp.json().await.map_err(ProxmoxError::Request)?;
+        Ok(match v.get_mut("data") {
+            Some(data) => data.take(),
+            None => v,
+        })
+    }
+
+    /// Sends a request with the current credentials, logging in again and
+    /// resending once if PVE rejected the ticket.
+    async fn send_authenticated(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> PveResult<reqwest::Response> {
+        let session = self.current_session();
+        let resp = self.send(method, path, body, &session).await?;
+
+        if resp.status() == StatusCode::UNAUTHORIZED
+            && self.api_token.is_none()
+            && self.credentials().is_some()
+        {
+            self.reauthenticate(session.as_ref().map(|s| s.generation))
+                .await?;
+            return self.send(method, path, body, &self.current_session()).await;
         }
+        Ok(resp)
+    }
 
-        let v: Value = resp.json().await.map_err(ProxmoxError::Request)?;
-        if let Some(data) = v.get("data") {
-            serde_json::from_value(data.clone()).map_err(ProxmoxError::Json)
-        } else {
-            serde_json::from_value(v).map_err(ProxmoxError::Json)
+    async fn send(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Option<&Value>,
+        session: &Option<TicketSession>,
+    ) -> PveResult<reqwest::Response> {
+        self.execute(method, path, |url| {
+            let mut req = self.client.request(method.clone(), url);
+
+            if let Some(token) = &self.api_token {
+                req = req.header("Authorization", token);
+            } else if let Some(s) = session {
+                req = req.header("CSRFPreventionToken", &s.csrf_token);
+                req = req.header("Cookie", format!("PVEAuthCookie={}", s.ticket));
+            }
+
+            if let Some(b) = body {
+                req = req.json(b);
+            }
+            req
+        })
+        .await
+    }
+
+    /// Sends a request to the first reachable API endpoint. Connection
+    /// failures always move on to the next endpoint since the request never
+    /// reached PVE. Timeouts and unavailable-host responses mark the
+    /// endpoint unhealthy but are only retried elsewhere for GETs, as a
+    /// mutating call may already have reached PVE and must not run twice.
+    async fn execute<F>(
+        &self,
+        method: &Method,
+        path: &str,
+        build: F,
+    ) -> PveResult<reqwest::Response>
+    where
+        F: Fn(Url) -> reqwest::RequestBuilder,
+    {
+        let candidates = self.endpoints.candidates();
+        let mut last_err = None;
+
+        for (i, base) in candidates.iter().enumerate() {
+            let url = base.join(path)?;
+            let is_last = i + 1 == candidates.len();
+
+            let permit = self.limiter.acquire(base).await.inspect_err(|e| {
+                warn!("{} {}: {}", method, path, e);
+                self.metrics.incr(QUEUE_TIMEOUTS, method);
+            })?;
+            if permit.queued {
+                self.metrics.incr(QUEUED, method);
+            }
+            self.metrics.incr(REQUESTS, method);
+            let sent = build(url).send().await;
+            drop(permit);
+            match sent {
+                Ok(resp) if is_unavailable(resp.status()) => {
+                    warn!("PVE endpoint {} returned {}", base, resp.status());
+                    self.endpoints.mark_down(base);
+                    if *method != Method::GET || is_last {
+                        return Ok(resp);
+                    }
+                }
+                Ok(resp) => {
+                    self.endpoints.mark_up(base);
+                    return Ok(resp);
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => {
+                    warn!("PVE endpoint {} unreachable: {}", base, e);
+                    self.endpoints.mark_down(base);
+                    if !e.is_connect() && *method != Method::GET {
+                        return Err(ProxmoxError::Request(e));
+                    }
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(ProxmoxError::Request(e)),
+            }
         }
+
+        Err(ProxmoxError::Request(
+            last_err.expect("endpoint pool is never empty"),
+        ))
     }
 }
@@ -1,4 +1,4 @@
-use super::client::ProxmoxClient;
+use super::client::{ProxmoxClient, TaskInfo, TaskStatus};
 use anyhow::Result;
 use reqwest::Method;
 use serde_json::{json, Value};
@@ -66,12 +66,23 @@ impl ProxmoxClient {
         Ok(self.request(Method::DELETE, &path, None).await?)
     }
 
-    pub async fn get_task_status(&self, node: &str, upid: &str) -> Result<Value> {
+    pub async fn get_task_status(&self, node: &str, upid: &str) -> Result<TaskStatus> {
         let path = format!("nodes/{}/tasks/{}/status", node, upid);
         Ok(self.request(Method::GET, &path, None).await?)
     }
 
-    pub async fn wait_for_task(&self, node: &str, upid: &str, timeout_secs: u64) -> Result<Value> {
+    pub async fn stop_task(&self, node: &str, upid: &str) -> Result<()> {
+        let path = format!("nodes/{}/tasks/{}", node, upid);
+        let _: Value = self.request(Method::DELETE, &path, None).await?;
+        Ok(())
+    }
+
+    pub async fn wait_for_task(
+        &self,
+        node: &str,
+        upid: &str,
+        timeout_secs: u64,
+    ) -> Result<TaskStatus> {
         let start_time = std::time::Instant::now();
         let timeout_duration = std::time::Duration::from_secs(timeout_secs);
 
@@ -85,11 +96,8 @@ impl ProxmoxClient {
             }
 
             let status = self.get_task_status(node, upid).await?;
-
-            if let Some(s) = status.get("status").and_then(|v| v.as_str()) {
-                if s == "stopped" {
-                    return Ok(status);
-                }
+            if status.is_stopped() {
+                return Ok(status);
             }
 
             tokio::time::sleep(std::time::Duration::from_secs(2)).await;
@@ -101,7 +109,22 @@ impl ProxmoxClient {
         Ok(self.request(Method::GET, &path, None).await?)
     }
 
-    pub async fn list_tasks(&self, node: &str, limit: Option<u64>) -> Result<Vec<Value>> {
+    /// Reads up to `limit` task log lines after the first `start` ones.
+    pub async fn get_task_log_page(
+        &self,
+        node: &str,
+        upid: &str,
+        start: u64,
+        limit: u64,
+    ) -> Result<Vec<Value>> {
+        let path = format!(
+            "nodes/{}/tasks/{}/log?start={}&limit={}",
+            node, upid, start, limit
+        );
+        Ok(self.request(Method::GET, &path, None).await?)
+    }
+
+    pub async fn list_tasks(&self, node: &str, limit: Option<u64>) -> Result<Vec<TaskInfo>> {
         let mut path = format!("nodes/{}/tasks", node);
         if let Some(l) = limit {
             path.push_str(&format!("?limit={}", l));
@@ -0,0 +1,151 @@
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// First backoff applied to an endpoint after a failure; doubles with each
+/// consecutive failure up to `MAX_BACKOFF`.
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+struct Endpoint {
+    url: Url,
+    failures: u32,
+    down_until: Option<Instant>,
+}
+
+/// The API endpoints of one cluster, in order of preference. The first
+/// entry is the configured primary host.
+pub(crate) struct EndpointPool {
+    endpoints: RwLock<Vec<Endpoint>>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub healthy: bool,
+    pub failures: u32,
+}
+
+impl EndpointPool {
+    pub(crate) fn new(primary: Url) -> Self {
+        Self {
+            endpoints: RwLock::new(vec![Endpoint {
+                url: primary,
+                failures: 0,
+                down_until: None,
+            }]),
+        }
+    }
+
+    /// Adds a fallback endpoint. Returns false if it was already known.
+    pub(crate) fn add(&self, url: Url) -> bool {
+        let mut endpoints = self.endpoints.write().unwrap();
+        if endpoints.iter().any(|e| e.url == url) {
+            return false;
+        }
+        endpoints.push(Endpoint {
+            url,
+            failures: 0,
+            down_until: None,
+        });
+        true
+    }
+
+    /// Endpoints to try for the next request: healthy ones in order of
+    /// preference, then those still backing off, soonest to recover first.
+    /// A backed-off primary is preferred again as soon as its backoff ends.
+    pub(crate) fn candidates(&self) -> Vec<Url> {
+        let now = Instant::now();
+        let endpoints = self.endpoints.read().unwrap();
+        let (healthy, mut down): (Vec<&Endpoint>, Vec<&Endpoint>) = endpoints
+            .iter()
+            .partition(|e| e.down_until.is_none_or(|t| t <= now));
+        down.sort_by_key(|e| e.down_until);
+        healthy
+            .into_iter()
+            .chain(down)
+            .map(|e| e.url.clone())
+            .collect()
+    }
+
+    pub(crate) fn mark_up(&self, url: &Url) {
+        let mut endpoints = self.endpoints.write().unwrap();
+        if let Some(e) = endpoints.iter_mut().find(|e| e.url == *url) {
+            e.failures = 0;
+            e.down_until = None;
+        }
+    }
+
+    pub(crate) fn mark_down(&self, url: &Url) {
+        let mut endpoints = self.endpoints.write().unwrap();
+        if let Some(e) = endpoints.iter_mut().find(|e| e.url == *url) {
+            let backoff = BASE_BACKOFF
+                .saturating_mul(1 << e.failures.min(16))
+                .min(MAX_BACKOFF);
+            e.failures += 1;
+            e.down_until = Some(Instant::now() + backoff);
+        }
+    }
+
+    pub(crate) fn health(&self) -> Vec<EndpointHealth> {
+        let now = Instant::now();
+        self.endpoints
+            .read()
+            .unwrap()
+            .iter()
+            .map(|e| EndpointHealth {
+                url: e.url.to_string(),
+                healthy: e.down_until.is_none_or(|t| t <= now),
+                failures: e.failures,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{}:8006/api2/json/", host)).unwrap()
+    }
+
+    #[test]
+    fn test_failover_order() {
+        let pool = EndpointPool::new(url("pve1"));
+        assert!(pool.add(url("pve2")));
+        assert!(pool.add(url("pve3")));
+        assert!(!pool.add(url("pve2")));
+
+        assert_eq!(
+            pool.candidates(),
+            vec![url("pve1"), url("pve2"), url("pve3")]
+        );
+
+        // A failed primary drops behind the healthy fallbacks
+        pool.mark_down(&url("pve1"));
+        assert_eq!(
+            pool.candidates(),
+            vec![url("pve2"), url("pve3"), url("pve1")]
+        );
+        assert!(!pool.health()[0].healthy);
+
+        // ...and is preferred again once it recovers
+        pool.mark_up(&url("pve1"));
+        assert_eq!(pool.candidates()[0], url("pve1"));
+        assert_eq!(pool.health()[0].failures, 0);
+    }
+
+    #[test]
+    fn test_backoff_grows() {
+        let pool = EndpointPool::new(url("pve1"));
+        pool.add(url("pve2"));
+        pool.mark_down(&url("pve1"));
+        pool.mark_down(&url("pve2"));
+        pool.mark_down(&url("pve2"));
+
+        // pve1 recovers sooner, so it is tried first when everything is down
+        assert_eq!(pool.candidates(), vec![url("pve1"), url("pve2")]);
+        assert_eq!(pool.health()[1].failures, 2);
+    }
+}
@@ -1,3 +1,6 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +8,10 @@ pub enum ProxmoxError {
     #[error("API request failed: {0} - {1}")]
     Api(reqwest::StatusCode, String),
 
+    /// A 400 listing what is wrong with each rejected parameter.
+    #[error("Invalid parameters: {}", describe_fields(.0))]
+    Validation(BTreeMap<String, String>),
+
     #[error("Authentication failed: {0}")]
     Auth(String),
 
@@ -33,4 +40,81 @@ pub enum ProxmoxError {
     Timeout(String),
 }
 
+impl ProxmoxError {
+    /// Whether the same request may succeed when sent again later. PVE
+    /// answers most rejected operations with a 500, so only gateway errors,
+    /// rate limiting and network failures count.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ProxmoxError::Api(status, _) => {
+                matches!(status.as_u16(), 429 | 502 | 503 | 504 | 595)
+            }
+            ProxmoxError::Request(e) => e.is_connect() || e.is_timeout(),
+            ProxmoxError::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The error for a failed API response. A 400 whose body carries PVE's
+    /// `errors` object, mapping parameter names to what is wrong with them,
+    /// becomes `Validation`.
+    pub fn from_response(status: StatusCode, body: String) -> Self {
+        if status == StatusCode::BAD_REQUEST {
+            let errors = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|v| v.get("errors").and_then(|e| e.as_object()).cloned());
+            if let Some(errors) = errors.filter(|e| !e.is_empty()) {
+                return ProxmoxError::Validation(
+                    errors
+                        .into_iter()
+                        .map(|(field, message)| {
+                            let message = match message {
+                                Value::String(s) => s.trim().to_string(),
+                                v => v.to_string(),
+                            };
+                            (field, message)
+                        })
+                        .collect(),
+                );
+            }
+        }
+        ProxmoxError::Api(status, body)
+    }
+}
+
+/// `field: message` pairs of per-parameter errors, for error messages.
+pub fn describe_fields(errors: &BTreeMap<String, String>) -> String {
+    errors
+        .iter()
+        .map(|(field, message)| format!("{}: {}", field, message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 pub type Result<T> = std::result::Result<T, ProxmoxError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response() {
+        let body = r#"{"data":null,"errors":{"memory":"value must be >= 16\n","cores":"type check failed"}}"#;
+        match ProxmoxError::from_response(StatusCode::BAD_REQUEST, body.to_string()) {
+            ProxmoxError::Validation(errors) => {
+                assert_eq!(errors["memory"], "value must be >= 16");
+                assert_eq!(
+                    describe_fields(&errors),
+                    "cores: type check failed; memory: value must be >= 16"
+                );
+            }
+            e => panic!("Expected Validation, got {:?}", e),
+        }
+
+        // Without an errors object the body is kept as is
+        let e = ProxmoxError::from_response(StatusCode::BAD_REQUEST, "bad".to_string());
+        assert!(matches!(e, ProxmoxError::Api(_, ref b) if b == "bad"));
+        let e = ProxmoxError::from_response(StatusCode::INTERNAL_SERVER_ERROR, body.to_string());
+        assert!(matches!(e, ProxmoxError::Api(..)));
+    }
+}
@@ -0,0 +1,203 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use url::Url;
+
+use crate::proxmox::error::{ProxmoxError, Result};
+
+/// Limits on the requests sent to each API host, from the `[rate_limit]`
+/// config section, so that agents cannot crowd out the web UI on the same
+/// `pveproxy`. Requests over a limit queue in arrival order.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Sustained requests per second per host; 0 disables the rate limit.
+    pub requests_per_sec: f64,
+    /// Requests that may be sent back to back before the rate applies.
+    pub burst: u32,
+    /// Requests awaiting a response per host at most; 0 means unlimited.
+    pub max_in_flight: usize,
+    /// How long a request may wait in the queue before it fails.
+    pub queue_timeout_ms: u64,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_sec: 0.0,
+            burst: 10,
+            max_in_flight: 0,
+            queue_timeout_ms: 30_000,
+        }
+    }
+}
+
+impl RateLimit {
+    fn is_enabled(&self) -> bool {
+        self.requests_per_sec > 0.0 || self.max_in_flight > 0
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+struct HostLimiter {
+    // The lock is held while waiting for a token, which keeps waiters in order
+    bucket: Mutex<Bucket>,
+    in_flight: Arc<Semaphore>,
+}
+
+/// Keeps the request slot of a host until dropped.
+pub(crate) struct Permit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+    /// Whether the request had to wait for its slot.
+    pub(crate) queued: bool,
+}
+
+/// The limiters of every API host of one cluster.
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    hosts: DashMap<Url, Arc<HostLimiter>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            hosts: DashMap::new(),
+        }
+    }
+
+    fn host(&self, url: &Url) -> Arc<HostLimiter> {
+        self.hosts
+            .entry(url.clone())
+            .or_insert_with(|| {
+                Arc::new(HostLimiter {
+                    bucket: Mutex::new(Bucket {
+                        tokens: self.burst(),
+                        refilled: Instant::now(),
+                    }),
+                    in_flight: Arc::new(Semaphore::new(self.limit.max_in_flight)),
+                })
+            })
+            .clone()
+    }
+
+    fn burst(&self) -> f64 {
+        f64::from(self.limit.burst.max(1))
+    }
+
+    /// Waits until a request may be sent to `url`, or fails with
+    /// `ProxmoxError::Timeout` once the queue timeout has passed.
+    pub(crate) async fn acquire(&self, url: &Url) -> Result<Permit> {
+        if !self.limit.is_enabled() {
+            return Ok(Permit {
+                _in_flight: None,
+                queued: false,
+            });
+        }
+        let host = self.host(url);
+        let timeout = Duration::from_millis(self.limit.queue_timeout_ms);
+
+        let acquire = async {
+            let mut queued = false;
+            let permit = match self.limit.max_in_flight {
+                0 => None,
+                _ => match host.in_flight.clone().try_acquire_owned() {
+                    Ok(permit) => Some(permit),
+                    Err(_) => {
+                        queued = true;
+                        host.in_flight.clone().acquire_owned().await.ok()
+                    }
+                },
+            };
+            if self.limit.requests_per_sec > 0.0 {
+                let mut bucket = match host.bucket.try_lock() {
+                    Ok(bucket) => bucket,
+                    Err(_) => {
+                        queued = true;
+                        host.bucket.lock().await
+                    }
+                };
+                let rate = self.limit.requests_per_sec;
+                let now = Instant::now();
+                bucket.tokens = (bucket.tokens
+                    + now.duration_since(bucket.refilled).as_secs_f64() * rate)
+                    .min(self.burst());
+                bucket.refilled = now;
+                if bucket.tokens < 1.0 {
+                    queued = true;
+                    tokio::time::sleep(Duration::from_secs_f64((1.0 - bucket.tokens) / rate)).await;
+                    bucket.tokens = 1.0;
+                    bucket.refilled = Instant::now();
+                }
+                bucket.tokens -= 1.0;
+            }
+            Permit {
+                _in_flight: permit,
+                queued,
+            }
+        };
+
+        tokio::time::timeout(timeout, acquire).await.map_err(|_| {
+            ProxmoxError::Timeout(format!(
+                "no request slot for {} within {}ms",
+                url.host_str().unwrap_or_default(),
+                self.limit.queue_timeout_ms
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{}:8006/api2/json/", host)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_sec: 20.0,
+            burst: 2,
+            ..RateLimit::default()
+        });
+        let started = Instant::now();
+        let mut queued = Vec::new();
+        for _ in 0..4 {
+            queued.push(limiter.acquire(&url("pve1")).await.unwrap().queued);
+        }
+        assert_eq!(queued, [false, false, true, true]);
+        // Two from the burst, then one every 50ms
+        assert!(started.elapsed() >= Duration::from_millis(90));
+
+        // Other hosts have their own bucket
+        let started = Instant::now();
+        assert!(!limiter.acquire(&url("pve2")).await.unwrap().queued);
+        assert!(started.elapsed() < Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let limiter = RateLimiter::new(RateLimit {
+            max_in_flight: 1,
+            queue_timeout_ms: 50,
+            ..RateLimit::default()
+        });
+        let permit = limiter.acquire(&url("pve1")).await.unwrap();
+        assert!(matches!(
+            limiter.acquire(&url("pve1")).await,
+            Err(ProxmoxError::Timeout(_))
+        ));
+        assert!(limiter.acquire(&url("pve2")).await.is_ok());
+
+        drop(permit);
+        assert!(limiter.acquire(&url("pve1")).await.is_ok());
+    }
+}
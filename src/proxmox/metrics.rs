@@ -0,0 +1,98 @@
+use dashmap::DashMap;
+use reqwest::Method;
+use std::fmt::Write;
+
+pub const REQUESTS: &str = "proxmox_api_requests_total";
+pub const RETRIES: &str = "proxmox_api_retries_total";
+pub const RETRIES_EXHAUSTED: &str = "proxmox_api_retries_exhausted_total";
+pub const QUEUED: &str = "proxmox_api_queued_total";
+pub const QUEUE_TIMEOUTS: &str = "proxmox_api_queue_timeouts_total";
+pub const CACHE_HITS: &str = "proxmox_api_cache_hits_total";
+
+/// Counters exported at `/metrics`, with their help text.
+pub const METRICS: &[(&str, &str)] = &[
+    (
+        REQUESTS,
+        "PVE API requests sent, each retry counted separately",
+    ),
+    (
+        RETRIES,
+        "PVE API requests repeated after a transient failure",
+    ),
+    (
+        RETRIES_EXHAUSTED,
+        "PVE API requests that still failed transiently when retries ran out",
+    ),
+    (
+        QUEUED,
+        "PVE API requests that waited for the rate limit or a free request slot",
+    ),
+    (
+        QUEUE_TIMEOUTS,
+        "PVE API requests that failed after waiting too long in the queue",
+    ),
+    (CACHE_HITS, "PVE API GETs answered from the response cache"),
+];
+
+/// Counters of one client's API traffic by metric name and HTTP method,
+/// shared by every clone of the client.
+#[derive(Debug, Default)]
+pub struct ApiMetrics {
+    counters: DashMap<(&'static str, String), u64>,
+}
+
+impl ApiMetrics {
+    pub fn incr(&self, name: &'static str, method: &Method) {
+        *self.counters.entry((name, method.to_string())).or_default() += 1;
+    }
+
+    /// `(method, value)` of every sample of a metric, sorted by method.
+    fn samples(&self, name: &str) -> Vec<(String, u64)> {
+        let mut samples: Vec<(String, u64)> = self
+            .counters
+            .iter()
+            .filter(|e| e.key().0 == name)
+            .map(|e| (e.key().1.clone(), *e.value()))
+            .collect();
+        samples.sort();
+        samples
+    }
+}
+
+/// The metrics of every cluster in the Prometheus text format.
+pub fn render<'a>(clusters: impl IntoIterator<Item = (&'a str, &'a ApiMetrics)> + Clone) -> String {
+    let mut out = String::new();
+    for (name, help) in METRICS {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for (cluster, metrics) in clusters.clone() {
+            for (method, value) in metrics.samples(name) {
+                let _ = writeln!(
+                    out,
+                    "{}{{cluster=\"{}\",method=\"{}\"}} {}",
+                    name, cluster, method, value
+                );
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = ApiMetrics::default();
+        metrics.incr(REQUESTS, &Method::GET);
+        metrics.incr(REQUESTS, &Method::GET);
+        metrics.incr(RETRIES, &Method::POST);
+
+        let text = render([("default", &metrics)]);
+        assert!(text.contains("# TYPE proxmox_api_retries_total counter\n"));
+        assert!(text.contains("proxmox_api_requests_total{cluster=\"default\",method=\"GET\"} 2\n"));
+        assert!(text.contains("proxmox_api_retries_total{cluster=\"default\",method=\"POST\"} 1\n"));
+        assert!(!text.contains("proxmox_api_retries_exhausted_total{"));
+    }
+}
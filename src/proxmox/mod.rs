@@ -1,11 +1,16 @@
 pub mod access;
 pub mod agent;
+pub mod cache;
 pub mod client;
 pub mod cluster;
+pub mod endpoints;
 pub mod error;
 pub mod hardware;
+pub mod limits;
+pub mod metrics;
 pub mod pool;
 pub mod replication;
+pub mod retry;
 pub mod snapshot;
 pub mod storage;
 pub mod subscription;
@@ -0,0 +1,120 @@
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often and how patiently failed API requests are repeated, from the
+/// `[retry]` config section. GETs are retried on any transient failure;
+/// other methods only when the connection failed before the request was
+/// sent, so a mutating call never reaches PVE twice.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries per HTTP method. Methods not listed are not retried.
+    pub methods: BTreeMap<String, u32>,
+    /// Delay before the first retry; doubles with each further retry.
+    pub base_delay_ms: u64,
+    /// Upper bound of a single delay. A `Retry-After` asking for longer
+    /// ends the retries.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            methods: BTreeMap::from([
+                ("GET".to_string(), 3),
+                ("POST".to_string(), 2),
+                ("PUT".to_string(), 2),
+                ("DELETE".to_string(), 2),
+            ]),
+            base_delay_ms: 250,
+            max_delay_ms: 10_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn max_retries(&self, method: &Method) -> u32 {
+        self.methods
+            .iter()
+            .find(|(m, _)| m.eq_ignore_ascii_case(method.as_str()))
+            .map_or(0, |(_, retries)| *retries)
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+
+    /// Delay before retry number `retry` (counting from 0): exponential
+    /// backoff with jitter, between half and all of `base * 2^retry`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(1 << retry.min(20))
+            .min(self.max_delay_ms);
+        Duration::from_millis(fastrand::u64(ceiling - ceiling / 2..=ceiling))
+    }
+}
+
+/// Whether the API host itself failed rather than the request: gateway
+/// errors and pveproxy's 595 for a node it cannot reach. PVE answers
+/// ordinary errors such as "VM 100 already running" with a 500, which
+/// says nothing about the host.
+pub fn is_unavailable(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 502 | 503 | 504 | 595)
+}
+
+/// Whether a GET that failed with this response may succeed when repeated:
+/// rate limiting, an unavailable host, and 500s caused by a pmxcfs lock or
+/// request timeout, whose body mentions the timeout (`got timeout`).
+pub fn is_transient(status: StatusCode, body: &str) -> bool {
+    match status.as_u16() {
+        429 => true,
+        500 => body.contains("timeout"),
+        _ => is_unavailable(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+            ..RetryPolicy::default()
+        };
+        for _ in 0..20 {
+            let first = policy.backoff(0);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.backoff(2);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            assert!(policy.backoff(30) <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.max_retries(&Method::GET), 3);
+        assert_eq!(policy.max_retries(&Method::PATCH), 0);
+
+        assert!(is_transient(StatusCode::SERVICE_UNAVAILABLE, ""));
+        assert!(is_transient(StatusCode::from_u16(595).unwrap(), ""));
+        assert!(is_transient(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "got timeout"
+        ));
+        assert!(!is_transient(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "VM 100 already running"
+        ));
+        assert!(!is_transient(StatusCode::BAD_REQUEST, "timeout"));
+
+        assert!(is_unavailable(StatusCode::BAD_GATEWAY));
+        assert!(!is_unavailable(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
@@ -1,7 +1,7 @@
-use super::client::ProxmoxClient;
+use super::client::{ProxmoxClient, SnapshotInfo};
 use anyhow::Result;
 use reqwest::Method;
-use serde_json::{json, Value};
+use serde_json::json;
 
 impl ProxmoxClient {
     pub async fn get_snapshots(
@@ -9,7 +9,7 @@ impl ProxmoxClient {
         node: &str,
         vmid: i64,
         resource_type: &str,
-    ) -> Result<Vec<Value>> {
+    ) -> Result<Vec<SnapshotInfo>> {
         let path = format!("nodes/{}/{}/{}/snapshot", node, resource_type, vmid);
         Ok(self.request(Method::GET, &path, None).await?)
     }
@@ -11,6 +11,19 @@ impl ProxmoxClient {
         Ok(self.request(Method::GET, &path, None).await?)
     }
 
+    pub async fn get_node_status(&self, node: &str) -> Result<Value> {
+        let path = format!("nodes/{}/status", node);
+        Ok(self.request(Method::GET, &path, None).await?)
+    }
+
+    pub async fn get_node_syslog(&self, node: &str, limit: Option<u64>) -> Result<Vec<Value>> {
+        let mut path = format!("nodes/{}/syslog", node);
+        if let Some(l) = limit {
+            path.push_str(&format!("?limit={}", l));
+        }
+        Ok(self.request(Method::GET, &path, None).await?)
+    }
+
     pub async fn get_node_stats(
         &self,
         node: &str,
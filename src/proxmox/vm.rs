@@ -1,10 +1,10 @@
-use super::client::{ClusterResource, ProxmoxClient, VmInfo};
+use super::client::{ClusterResource, NodeInfo, ProxmoxClient, VmInfo};
 use anyhow::Result;
 use reqwest::Method;
 use serde_json::{json, Value};
 
 impl ProxmoxClient {
-    pub async fn get_nodes(&self) -> Result<Vec<Value>> {
+    pub async fn get_nodes(&self) -> Result<Vec<NodeInfo>> {
         Ok(self.request(Method::GET, "nodes", None).await?)
     }
 
@@ -19,6 +19,8 @@ impl ProxmoxClient {
                 status: r.status.unwrap_or("unknown".to_string()),
                 node: Some(r.node),
                 vm_type: Some(r.res_type),
+                pool: r.pool,
+                tags: r.tags,
             })
             .collect();
         Ok(vms)
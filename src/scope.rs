@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Restricts the guests and nodes tools may act on, from the `[scope]`
+/// config section. Every non-empty criterion must match; within one
+/// criterion any entry may match. Empty criteria are unrestricted.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ResourceScope {
+    /// VMID ranges such as `"100-199"` or single IDs such as `"250"`.
+    #[serde(default)]
+    pub vmids: Vec<VmidRange>,
+    /// Resource pools a guest must belong to.
+    #[serde(default)]
+    pub pools: Vec<String>,
+    /// Tags of which a guest must carry at least one.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Nodes that tools may address and guests must run on.
+    #[serde(default)]
+    pub nodes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VmidRange {
+    start: i64,
+    end: i64,
+}
+
+impl TryFrom<String> for VmidRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("Invalid VMID range '{}'", s))
+        };
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => {
+                let id = parse(&s)?;
+                (id, id)
+            }
+        };
+        if start > end {
+            return Err(format!("Invalid VMID range '{}': start is after end", s));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl From<VmidRange> for String {
+    fn from(r: VmidRange) -> Self {
+        r.to_string()
+    }
+}
+
+impl fmt::Display for VmidRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl ResourceScope {
+    pub fn is_unrestricted(&self) -> bool {
+        self.vmids.is_empty()
+            && self.pools.is_empty()
+            && self.tags.is_empty()
+            && self.nodes.is_empty()
+    }
+
+    pub fn check_vmid(&self, vmid: i64) -> Result<(), String> {
+        if self.vmids.is_empty() || self.vmids.iter().any(|r| (r.start..=r.end).contains(&vmid)) {
+            Ok(())
+        } else {
+            Err(format!("VMID {} is outside the allowed VMID ranges", vmid))
+        }
+    }
+
+    pub fn check_node(&self, node: &str) -> Result<(), String> {
+        if self.nodes.is_empty() || self.nodes.iter().any(|n| n == node) {
+            Ok(())
+        } else {
+            Err(format!("Node '{}' is outside the allowed nodes", node))
+        }
+    }
+
+    /// Checks a guest against every criterion. `tags` is a PVE tag list
+    /// separated by `;` or `,`. A node of `None` skips the node check.
+    pub fn check_guest(
+        &self,
+        vmid: i64,
+        node: Option<&str>,
+        pool: Option<&str>,
+        tags: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_vmid(vmid)?;
+        if let Some(node) = node {
+            self.check_node(node)?;
+        }
+        if !self.pools.is_empty() && !pool.is_some_and(|p| self.pools.iter().any(|s| s == p)) {
+            return Err(format!("Guest {} is not in an allowed pool", vmid));
+        }
+        if !self.tags.is_empty() {
+            let has_tag = tags
+                .unwrap_or_default()
+                .split([';', ','])
+                .map(str::trim)
+                .any(|t| self.tags.iter().any(|s| s == t));
+            if !has_tag {
+                return Err(format!("Guest {} carries none of the allowed tags", vmid));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn permits_guest(
+        &self,
+        vmid: i64,
+        node: Option<&str>,
+        pool: Option<&str>,
+        tags: Option<&str>,
+    ) -> bool {
+        self.check_guest(vmid, node, pool, tags).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(s: &str) -> VmidRange {
+        VmidRange::try_from(s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_vmid_range_parsing() {
+        assert_eq!(
+            range("100-199"),
+            VmidRange {
+                start: 100,
+                end: 199
+            }
+        );
+        assert_eq!(
+            range(" 250 "),
+            VmidRange {
+                start: 250,
+                end: 250
+            }
+        );
+        assert_eq!(range("100-199").to_string(), "100-199");
+        assert_eq!(range("250").to_string(), "250");
+        assert!(VmidRange::try_from("200-100".to_string()).is_err());
+        assert!(VmidRange::try_from("abc".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_scope_checks() {
+        assert!(ResourceScope::default().permits_guest(1, Some("pve1"), None, None));
+
+        let scope = ResourceScope {
+            vmids: vec![range("100-199"), range("250")],
+            pools: vec!["team-a".into()],
+            tags: vec!["agent".into(), "lab".into()],
+            nodes: vec!["pve1".into()],
+        };
+        assert!(!scope.is_unrestricted());
+        assert!(scope.permits_guest(150, Some("pve1"), Some("team-a"), Some("web;lab")));
+        assert!(scope.permits_guest(250, None, Some("team-a"), Some("agent")));
+        assert!(!scope.permits_guest(200, Some("pve1"), Some("team-a"), Some("agent")));
+        assert!(!scope.permits_guest(150, Some("pve2"), Some("team-a"), Some("agent")));
+        assert!(!scope.permits_guest(150, Some("pve1"), Some("team-b"), Some("agent")));
+        assert!(!scope.permits_guest(150, Some("pve1"), None, Some("agent")));
+        assert!(!scope.permits_guest(150, Some("pve1"), Some("team-a"), Some("web")));
+        assert!(!scope.permits_guest(150, Some("pve1"), Some("team-a"), None));
+    }
+}
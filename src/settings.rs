@@ -1,7 +1,32 @@
+use crate::audit::AuditSettings;
+use crate::guard::GuestGuard;
+use crate::policy::ToolPolicy;
+use crate::proxmox::cache::CacheSettings;
+use crate::proxmox::limits::RateLimit;
+use crate::proxmox::retry::RetryPolicy;
+use crate::scope::ResourceScope;
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::Path;
 
+/// Name used for the cluster built from the top-level connection settings.
+pub const DEFAULT_CLUSTER: &str = "default";
+
+/// Connection settings for one named Proxmox cluster (`[clusters.<name>]`).
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ClusterSettings {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub token_name: Option<String>,
+    pub token_value: Option<String>,
+    pub no_verify_ssl: Option<bool>,
+    pub failover_hosts: Option<Vec<String>>,
+    pub discover_nodes: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct Settings {
     pub host: Option<String>,
@@ -11,6 +36,8 @@ pub struct Settings {
     pub token_name: Option<String>,
     pub token_value: Option<String>,
     pub no_verify_ssl: Option<bool>,
+    pub failover_hosts: Option<Vec<String>>,
+    pub discover_nodes: Option<bool>,
     #[allow(dead_code)]
     pub log_level: Option<String>,
     #[allow(dead_code)]
@@ -26,6 +53,22 @@ pub struct Settings {
     pub http_port: Option<u16>,
     pub http_auth_token: Option<String>,
     pub lazy_mode: Option<bool>,
+    pub read_only: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub confirm_destructive: Option<bool>,
+    pub confirm_ttl_secs: Option<u64>,
+    pub stop_task_on_cancel: Option<bool>,
+    pub subscription_poll_secs: Option<u64>,
+    pub prompts_dir: Option<String>,
+    pub policy: Option<ToolPolicy>,
+    pub scope: Option<ResourceScope>,
+    pub guard: Option<GuestGuard>,
+    pub audit: Option<AuditSettings>,
+    pub retry: Option<RetryPolicy>,
+    pub rate_limit: Option<RateLimit>,
+    pub cache: Option<CacheSettings>,
+    pub clusters: Option<BTreeMap<String, ClusterSettings>>,
+    pub default_cluster: Option<String>,
 }
 
 impl Settings {
@@ -54,6 +97,60 @@ impl Settings {
         s.build()?.try_deserialize()
     }
 
+    /// Returns the clusters to connect to. When no `[clusters]` table is
+    /// configured, the top-level connection settings form a single cluster
+    /// named [`DEFAULT_CLUSTER`].
+    pub fn cluster_settings(&self) -> BTreeMap<String, ClusterSettings> {
+        match &self.clusters {
+            Some(clusters) if !clusters.is_empty() => clusters.clone(),
+            _ => BTreeMap::from([(
+                DEFAULT_CLUSTER.to_string(),
+                ClusterSettings {
+                    host: self.host.clone(),
+                    port: self.port,
+                    user: self.user.clone(),
+                    password: self.password.clone(),
+                    token_name: self.token_name.clone(),
+                    token_value: self.token_value.clone(),
+                    no_verify_ssl: self.no_verify_ssl,
+                    failover_hosts: self.failover_hosts.clone(),
+                    discover_nodes: self.discover_nodes,
+                },
+            )]),
+        }
+    }
+
+    /// The cluster used when a tool call does not name one.
+    pub fn default_cluster_name(&self) -> String {
+        if let Some(name) = &self.default_cluster {
+            return name.clone();
+        }
+        self.cluster_settings()
+            .into_keys()
+            .next()
+            .unwrap_or_else(|| DEFAULT_CLUSTER.to_string())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let clusters = self.cluster_settings();
+        if self.clusters.as_ref().is_none_or(|c| c.is_empty()) {
+            return clusters[DEFAULT_CLUSTER].validate();
+        }
+
+        for (name, cluster) in &clusters {
+            cluster
+                .validate()
+                .map_err(|e| format!("Cluster '{}': {}", name, e))?;
+        }
+        let default = self.default_cluster_name();
+        if !clusters.contains_key(&default) {
+            return Err(format!("Default cluster '{}' is not configured", default));
+        }
+        Ok(())
+    }
+}
+
+impl ClusterSettings {
     pub fn validate(&self) -> Result<(), String> {
         if self.host.is_none() || self.host.as_ref().unwrap().is_empty() {
             return Err("Host is required".to_string());
@@ -123,16 +220,7 @@ mod tests {
             token_name: None,
             token_value: None,
             no_verify_ssl: Some(false),
-            log_level: None,
-            log_file_enable: None,
-            log_dir: None,
-            log_filename: None,
-            log_rotate: None,
-            server_type: None,
-            http_host: None,
-            http_port: None,
-            http_auth_token: None,
-            lazy_mode: None,
+            ..Default::default()
         };
         assert!(s.validate().is_err());
     }
@@ -147,16 +235,7 @@ mod tests {
             token_name: Some("t".into()),
             token_value: Some("v".into()),
             no_verify_ssl: Some(false),
-            log_level: None,
-            log_file_enable: None,
-            log_dir: None,
-            log_filename: None,
-            log_rotate: None,
-            server_type: None,
-            http_host: None,
-            http_port: None,
-            http_auth_token: None,
-            lazy_mode: None,
+            ..Default::default()
         };
         assert!(s.validate().is_ok());
     }
@@ -171,17 +250,100 @@ mod tests {
             token_name: Some("t".into()),
             token_value: Some("v".into()),
             no_verify_ssl: Some(false),
-            log_level: None,
-            log_file_enable: None,
-            log_dir: None,
-            log_filename: None,
-            log_rotate: None,
-            server_type: None,
-            http_host: None,
-            http_port: None,
-            http_auth_token: None,
-            lazy_mode: None,
+            ..Default::default()
         };
         assert!(s.validate().is_err());
     }
+
+    #[test]
+    fn test_load_clusters_from_file() {
+        let mut file = Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            "default_cluster = 'prod'\n\
+             [clusters.prod]\nhost = 'pve-prod'\nuser = 'root@pam'\npassword = 'pw'\n\
+             [clusters.lab]\nhost = 'pve-lab'\nuser = 'root@pam'\ntoken_name = 't'\ntoken_value = 'v'"
+        )
+        .unwrap();
+
+        let path = file.path().to_str().unwrap();
+        let settings = Settings::new(Some(path)).unwrap();
+        assert!(settings.validate().is_ok());
+
+        let clusters = settings.cluster_settings();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters["lab"].host, Some("pve-lab".to_string()));
+        assert_eq!(settings.default_cluster_name(), "prod");
+    }
+
+    #[test]
+    fn test_load_policy_and_scope_from_file() {
+        let mut file = Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            "host = 'h'\nuser = 'u'\npassword = 'p'\n\
+             [policy]\ndeny = ['vm_exec']\n\
+             [scope]\nvmids = ['100-199', '250']\npools = ['team-a']"
+        )
+        .unwrap();
+
+        let path = file.path().to_str().unwrap();
+        let settings = Settings::new(Some(path)).unwrap();
+
+        let policy = settings.policy.unwrap();
+        assert!(!policy.permits("vm_exec"));
+        assert!(policy.permits("list_vms"));
+
+        let scope = settings.scope.unwrap();
+        assert!(scope.check_vmid(150).is_ok());
+        assert!(scope.check_vmid(250).is_ok());
+        assert!(scope.check_vmid(200).is_err());
+        assert_eq!(scope.pools, vec!["team-a".to_string()]);
+        assert!(scope.tags.is_empty());
+    }
+
+    #[test]
+    fn test_validation_clusters() {
+        let cluster = ClusterSettings {
+            host: Some("h".into()),
+            user: Some("u".into()),
+            password: Some("p".into()),
+            ..Default::default()
+        };
+        let mut s = Settings {
+            clusters: Some(BTreeMap::from([
+                ("prod".to_string(), cluster.clone()),
+                ("lab".to_string(), ClusterSettings::default()),
+            ])),
+            ..Default::default()
+        };
+        // Top-level host is not required, but every cluster must be complete
+        assert!(s.validate().unwrap_err().contains("Cluster 'lab'"));
+
+        s.clusters = Some(BTreeMap::from([("prod".to_string(), cluster)]));
+        assert!(s.validate().is_ok());
+        assert_eq!(s.default_cluster_name(), "prod");
+
+        s.default_cluster = Some("staging".to_string());
+        assert!(s.validate().is_err());
+    }
+
+    #[test]
+    fn test_load_retry_from_file() {
+        let mut file = Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            "host = 'h'\nuser = 'u'\npassword = 'p'\n\
+             [retry]\nmethods = {{ GET = 5, POST = 0 }}\nmax_delay_ms = 2000"
+        )
+        .unwrap();
+
+        let path = file.path().to_str().unwrap();
+        let retry = Settings::new(Some(path)).unwrap().retry.unwrap();
+        assert_eq!(retry.max_retries(&reqwest::Method::GET), 5);
+        assert_eq!(retry.max_retries(&reqwest::Method::POST), 0);
+        assert_eq!(retry.max_retries(&reqwest::Method::PUT), 0);
+        assert_eq!(retry.base_delay_ms, 250);
+        assert_eq!(retry.max_delay_ms, 2000);
+    }
 }
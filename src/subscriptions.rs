@@ -0,0 +1,238 @@
+use dashmap::DashMap;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::mcp::Notifier;
+use crate::proxmox::client::ClusterResource;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What a subscriber sees of a guest in `cluster/resources`.
+#[derive(Debug, Clone, PartialEq)]
+struct GuestState {
+    status: Option<String>,
+    node: String,
+    name: Option<String>,
+    tags: Option<String>,
+}
+
+/// Guests and node states from one `cluster/resources` poll.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    guests: HashMap<i64, GuestState>,
+    nodes: HashMap<String, Option<String>>,
+}
+
+impl Snapshot {
+    pub fn new<'a>(resources: impl IntoIterator<Item = &'a ClusterResource>) -> Self {
+        let mut snapshot = Self::default();
+        for r in resources {
+            match (r.res_type.as_str(), r.vmid) {
+                ("qemu" | "lxc", Some(vmid)) => {
+                    snapshot.guests.insert(
+                        vmid,
+                        GuestState {
+                            status: r.status.clone(),
+                            node: r.node.clone(),
+                            name: r.name.clone(),
+                            tags: r.tags.clone(),
+                        },
+                    );
+                }
+                ("node", _) => {
+                    snapshot.nodes.insert(r.node.clone(), r.status.clone());
+                }
+                _ => {}
+            }
+        }
+        snapshot
+    }
+
+    /// Guests that were created, deleted or changed status, node, name or
+    /// tags since `self`, and the nodes they were or are on or whose own
+    /// status changed.
+    pub fn changes(&self, new: &Snapshot) -> Changes {
+        let mut changes = Changes::default();
+        let vmids: BTreeSet<i64> = self
+            .guests
+            .keys()
+            .chain(new.guests.keys())
+            .copied()
+            .collect();
+        for vmid in vmids {
+            let (old, now) = (self.guests.get(&vmid), new.guests.get(&vmid));
+            if old != now {
+                changes.guests.insert(vmid);
+                changes
+                    .nodes
+                    .extend(old.into_iter().chain(now).map(|g| g.node.clone()));
+            }
+        }
+        for node in self.nodes.keys().chain(new.nodes.keys()) {
+            if self.nodes.get(node) != new.nodes.get(node) {
+                changes.nodes.insert(node.clone());
+            }
+        }
+        changes
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Changes {
+    guests: BTreeSet<i64>,
+    nodes: BTreeSet<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.guests.is_empty() && self.nodes.is_empty()
+    }
+
+    /// Whether the resource at `path` (a URI without `?cluster=`) changed.
+    pub fn affects(&self, path: &str) -> bool {
+        match subscribable(path) {
+            Some(Target::Vms) => !self.guests.is_empty(),
+            Some(Target::Guest(vmid)) => self.guests.contains(&vmid),
+            Some(Target::Node(node)) => self.nodes.contains(node),
+            None => false,
+        }
+    }
+}
+
+pub enum Target<'a> {
+    Vms,
+    Guest(i64),
+    Node(&'a str),
+}
+
+/// The guest or node a subscribable resource describes, or None if the
+/// resource does not support subscriptions.
+pub fn subscribable(path: &str) -> Option<Target<'_>> {
+    let segments: Vec<&str> = path.strip_prefix("proxmox://")?.split('/').collect();
+    match segments.as_slice() {
+        ["vms"] => Some(Target::Vms),
+        ["guest", vmid, "config" | "snapshots"] => vmid.parse().ok().map(Target::Guest),
+        ["node", node, "status" | "syslog"] => Some(Target::Node(node)),
+        _ => None,
+    }
+}
+
+/// Resource URIs subscribed to per session, each with the notifier that
+/// reaches its session.
+pub struct Subscriptions {
+    interval: Duration,
+    subscribed: DashMap<(Option<String>, String), Notifier>,
+    poller: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Subscriptions {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            subscribed: DashMap::new(),
+            poller: Mutex::new(None),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn subscribe(&self, session: Option<String>, uri: &str, notifier: Notifier) {
+        self.subscribed.insert((session, uri.to_string()), notifier);
+    }
+
+    pub fn unsubscribe(&self, session: Option<String>, uri: &str) {
+        self.subscribed.remove(&(session, uri.to_string()));
+    }
+
+    /// Drops every subscription of a session that has ended.
+    pub fn end_session(&self, session: Option<&str>) {
+        self.subscribed.retain(|(s, _), _| s.as_deref() != session);
+    }
+
+    pub fn uris(&self) -> BTreeSet<String> {
+        self.subscribed.iter().map(|e| e.key().1.clone()).collect()
+    }
+
+    /// Sends `notifications/resources/updated` for every subscribed URI
+    /// matching `changed`. Subscriptions whose session is gone are dropped.
+    pub fn notify(&self, changed: impl Fn(&str) -> bool) {
+        self.subscribed.retain(|(_, uri), notifier| {
+            !changed(uri)
+                || notifier
+                    .send(json!({
+                        "jsonrpc": "2.0",
+                        "method": "notifications/resources/updated",
+                        "params": { "uri": uri }
+                    }))
+                    .is_ok()
+        });
+    }
+
+    /// Starts `poll` unless a poller is already running.
+    pub fn ensure_poller<F>(&self, poll: impl FnOnce() -> F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let mut poller = self.poller.lock().unwrap();
+        if poller.as_ref().is_none_or(|p| p.is_finished()) {
+            *poller = Some(tokio::spawn(poll()));
+        }
+    }
+
+    /// Called by the poller between rounds: true if it should stop because
+    /// nothing is subscribed any more.
+    pub fn poller_idle(&self) -> bool {
+        let mut poller = self.poller.lock().unwrap();
+        if self.subscribed.is_empty() {
+            *poller = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guest(vmid: i64, node: &str, status: &str) -> ClusterResource {
+        serde_json::from_value(json!({
+            "vmid": vmid, "node": node, "type": "qemu", "status": status
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_changes() {
+        let before = Snapshot::new(&[guest(100, "pve1", "running"), guest(101, "pve1", "stopped")]);
+
+        let same = Snapshot::new(&[guest(101, "pve1", "stopped"), guest(100, "pve1", "running")]);
+        assert!(before.changes(&same).is_empty());
+
+        // 100 migrated, 101 deleted, 102 created
+        let after = Snapshot::new(&[guest(100, "pve2", "running"), guest(102, "pve3", "running")]);
+        let changes = before.changes(&after);
+        assert!(changes.affects("proxmox://vms"));
+        assert!(changes.affects("proxmox://guest/100/config"));
+        assert!(changes.affects("proxmox://guest/101/snapshots"));
+        assert!(changes.affects("proxmox://guest/102/config"));
+        assert!(!changes.affects("proxmox://guest/103/config"));
+        assert!(changes.affects("proxmox://node/pve3/status"));
+        assert!(!changes.affects("proxmox://task/pve1/UPID:x/log"));
+    }
+
+    #[test]
+    fn test_subscribable() {
+        assert!(subscribable("proxmox://vms").is_some());
+        assert!(subscribable("proxmox://guest/100/config").is_some());
+        assert!(subscribable("proxmox://node/pve1/syslog").is_some());
+        assert!(subscribable("proxmox://guest/abc/config").is_none());
+        assert!(subscribable("proxmox://cluster/ha").is_none());
+    }
+}
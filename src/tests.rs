@@ -4,7 +4,7 @@ mod tests {
     use crate::proxmox::ProxmoxClient;
     use serde_json::json;
     use url::Url;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_string_contains, header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn create_test_client(uri: &str) -> ProxmoxClient {
@@ -154,6 +154,8 @@ mod tests {
         let res = server.call_tool("list_nodes", &json!({})).await.unwrap();
         let content = res["content"][0]["text"].as_str().unwrap();
         assert!(content.contains("pve1"));
+        assert_eq!(res["structuredContent"]["nodes"][0]["node"], "pve1");
+        assert_eq!(res["structuredContent"]["nodes"][0]["status"], "online");
     }
 
     #[tokio::test]
@@ -668,6 +670,7 @@ mod tests {
             .as_str()
             .unwrap()
             .contains("Task finished"));
+        assert_eq!(res["structuredContent"]["task"]["exitstatus"], "OK");
     }
 
     #[tokio::test]
@@ -783,6 +786,118 @@ mod tests {
         assert!(text.contains("running"));
     }
 
+    #[tokio::test]
+    async fn test_resource_templates() {
+        let mock_server = MockServer::start().await;
+        let upid = "UPID:pve1:0001:0002:65A0:qmstart:100:root@pam:";
+
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "vmid": 100, "node": "pve1", "type": "qemu", "status": "running" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "name": "web", "memory": 2048 }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "uptime": 1234, "pveversion": "pve-manager/8.2.2" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}/log", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "n": 1, "t": "TASK OK" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, false);
+        let request =
+            |method: &str, params: Option<serde_json::Value>| crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params,
+                id: Some(json!(1)),
+            };
+        let read = |uri: &str| request("resources/read", Some(json!({ "uri": uri })));
+
+        let res = server
+            .handle_request(request("resources/templates/list", None))
+            .await
+            .unwrap();
+        let templates = res["resourceTemplates"].as_array().unwrap();
+        for template in [
+            "proxmox://node/{node}/status",
+            "proxmox://guest/{vmid}/config",
+            "proxmox://task/{node}/{upid}/log",
+            "proxmox://storage/{node}/{storage}/content",
+        ] {
+            assert!(templates.iter().any(|t| t["uriTemplate"] == template));
+        }
+        // Fixed resources are listed by resources/list only
+        assert!(!templates
+            .iter()
+            .any(|t| t["uriTemplate"] == "proxmox://cluster/ha"));
+
+        let res = server
+            .handle_request(read("proxmox://guest/100/config"))
+            .await
+            .unwrap();
+        assert!(res["contents"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("2048"));
+
+        let res = server
+            .handle_request(read("proxmox://node/pve1/status"))
+            .await
+            .unwrap();
+        assert!(res["contents"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("pve-manager"));
+
+        // UPIDs may arrive percent-encoded
+        let encoded = upid.replace(':', "%3A").replace('@', "%40");
+        let res = server
+            .handle_request(read(&format!("proxmox://task/pve1/{}/log", encoded)))
+            .await
+            .unwrap();
+        assert!(res["contents"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("TASK OK"));
+
+        assert!(server
+            .handle_request(read("proxmox://guest/abc/config"))
+            .await
+            .is_err());
+
+        // Guest resources honor the resource scope
+        let mut scoped = server.clone();
+        scoped.set_scope(crate::scope::ResourceScope {
+            nodes: vec!["pve2".into()],
+            ..Default::default()
+        });
+        let err = scoped
+            .handle_request(read("proxmox://guest/100/config"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Out of scope"));
+    }
+
     #[tokio::test]
     async fn test_cluster_tools() {
         let mock_server = MockServer::start().await;
@@ -1778,13 +1893,21 @@ mod tests {
             params: Some(json!({ "name": "list_nodes", "arguments": {} })),
             id: Some(json!(1)),
         };
-        // We capture stdout in run_stdio, but handle_request returns Result<Value>.
-        // Wait, handle_request returns Err on failure. run_stdio wraps it.
-        // We can check the error returned by handle_request.
-        let res = server.handle_request(req).await;
-        assert!(res.is_err());
-        let err = res.err().unwrap();
-        // The error is anyhow::Error wrapping ProxmoxError.
+        // Failing tools are reported as results so the model sees the error
+        let res = server.handle_request(req).await.unwrap();
+        assert_eq!(res["isError"], true);
+        assert!(res["content"][0]["text"].as_str().unwrap().contains("401"));
+        let error: serde_json::Value =
+            serde_json::from_str(res["content"][1]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(error["code"], -32001);
+        assert_eq!(error["data"]["status"], 401);
+        assert_eq!(error["data"]["retryable"], false);
+
+        // Calling the tool directly still returns the ProxmoxError
+        let err = server
+            .call_tool("list_nodes", &json!({}))
+            .await
+            .unwrap_err();
         let pve_err = err.downcast_ref::<crate::proxmox::ProxmoxError>().unwrap();
         match pve_err {
             crate::proxmox::ProxmoxError::Api(status, _) => assert_eq!(status.as_u16(), 401),
@@ -1792,20 +1915,154 @@ mod tests {
         }
 
         // Test 404
-        let req = crate::mcp::JsonRpcRequest {
+        let err = server.call_tool("list_vms", &json!({})).await.unwrap_err();
+        let error = crate::mcp::json_rpc_error(&err);
+        assert_eq!(error.code, -32004);
+        assert_eq!(error.data.unwrap()["details"], "Not Found");
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_errors() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "data": null,
+                "errors": { "timeout": "value must be at least 0" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, false);
+
+        // PVE parameter errors become invalid params with the per-field messages
+        let err = server
+            .call_tool("start_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap_err();
+        let error = crate::mcp::json_rpc_error(&err);
+        assert_eq!(error.code, -32602);
+        let data = error.data.unwrap();
+        assert_eq!(data["status"], 400);
+        assert_eq!(data["errors"]["timeout"], "value must be at least 0");
+        assert_eq!(data["retryable"], false);
+
+        let err = server
+            .call_tool("list_nodes", &json!({}))
+            .await
+            .unwrap_err();
+        let error = crate::mcp::json_rpc_error(&err);
+        assert_eq!(error.code, -32603);
+        assert_eq!(error.data.unwrap()["retryable"], true);
+
+        // Refusals by the server stay JSON-RPC errors
+        let mut read_only = McpServer::new(create_test_client(&mock_server.uri()), false);
+        read_only.set_read_only(true);
+        let err = read_only
+            .handle_request(crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "tools/call".to_string(),
+                params: Some(
+                    json!({ "name": "start_vm", "arguments": { "node": "pve1", "vmid": 100 } }),
+                ),
+                id: Some(json!(1)),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(crate::mcp::json_rpc_error(&err).code, -32005);
+
+        let resp = crate::mcp::json_rpc_response(
+            json!(2),
+            server
+                .handle_request(crate::mcp::JsonRpcRequest {
+                    jsonrpc: "2.0".to_string(),
+                    method: "tools/unknown".to_string(),
+                    params: None,
+                    id: Some(json!(2)),
+                })
+                .await,
+        );
+        assert_eq!(resp.error.unwrap().code, -32601);
+
+        let err = server
+            .call_tool("no_such_tool", &json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(crate::mcp::json_rpc_error(&err).code, -32602);
+
+        // Messages that are not requests
+        let resp = crate::mcp::parse_request("{not json").unwrap_err();
+        assert_eq!(resp["error"]["code"], -32700);
+        assert_eq!(resp["id"], serde_json::Value::Null);
+        let resp = crate::mcp::parse_request(r#"{"jsonrpc":"2.0","id":7}"#).unwrap_err();
+        assert_eq!(resp["error"]["code"], -32600);
+        assert_eq!(resp["id"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_argument_validation() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("PUT"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "data": null,
+                "errors": { "memory": "value must have a minimum value of 16\n" }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, false);
+        let call = |arguments: serde_json::Value| crate::mcp::JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
             method: "tools/call".to_string(),
-            params: Some(json!({ "name": "list_vms", "arguments": {} })),
-            id: Some(json!(2)),
+            params: Some(json!({ "name": "update_vm_resources", "arguments": arguments })),
+            id: Some(json!(1)),
         };
-        let res = server.handle_request(req).await;
-        assert!(res.is_err());
-        let err = res.err().unwrap();
-        let pve_err = err.downcast_ref::<crate::proxmox::ProxmoxError>().unwrap();
-        match pve_err {
-            crate::proxmox::ProxmoxError::Api(status, _) => assert_eq!(status.as_u16(), 404),
-            _ => panic!("Expected Api error"),
-        }
+        let error_data = |res: &serde_json::Value| -> serde_json::Value {
+            assert_eq!(res["isError"], true);
+            serde_json::from_str(res["content"][1]["text"].as_str().unwrap()).unwrap()
+        };
+
+        // Every bad argument is reported at once, without calling PVE
+        let res = server
+            .handle_request(call(json!({ "vmid": "100", "cores": true })))
+            .await
+            .unwrap();
+        let error = error_data(&res);
+        assert_eq!(error["code"], -32602);
+        assert_eq!(
+            error["data"]["errors"],
+            json!({
+                "node": "is required",
+                "vmid": "must be of type integer",
+                "cores": "must be of type integer"
+            })
+        );
+
+        // PVE's per-parameter errors are passed on as a map
+        let res = server
+            .handle_request(call(json!({ "node": "pve1", "vmid": 100, "memory": 8 })))
+            .await
+            .unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("memory: value must have a minimum value of 16"));
+        let error = error_data(&res);
+        assert_eq!(error["code"], -32602);
+        assert_eq!(error["data"]["status"], 400);
+        assert_eq!(
+            error["data"]["errors"]["memory"],
+            "value must have a minimum value of 16"
+        );
     }
 
     #[tokio::test]
@@ -2016,4 +2273,1991 @@ mod tests {
             .unwrap()
             .contains("Device hostpci0 removed"));
     }
+
+    #[tokio::test]
+    async fn test_ticket_relogin_on_401() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ticket": "fresh", "CSRFPreventionToken": "csrf" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // First call is rejected as if the ticket had expired server-side
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Unauthorized"))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .and(header("Cookie", "PVEAuthCookie=fresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let mut client = create_test_client(&mock_server.uri());
+        client.login("root@pam", "secret").await.unwrap();
+        let server = McpServer::new(client, false);
+
+        let res = server.call_tool("list_nodes", &json!({})).await.unwrap();
+        assert!(res["content"][0]["text"].as_str().unwrap().contains("pve1"));
+
+        let logins = mock_server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.url.path() == "/api2/json/access/ticket")
+            .count();
+        assert_eq!(logins, 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_401_triggers_single_login() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ticket": "old", "CSRFPreventionToken": "csrf" }
+            })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ticket": "new", "CSRFPreventionToken": "csrf" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .and(header("Cookie", "PVEAuthCookie=old"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Unauthorized"))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .and(header("Cookie", "PVEAuthCookie=new"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [] })))
+            .mount(&mock_server)
+            .await;
+
+        let mut client = create_test_client(&mock_server.uri());
+        client.login("root@pam", "secret").await.unwrap();
+
+        let calls = (0..10).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.get_nodes().await })
+        });
+        for res in futures::future::join_all(calls).await {
+            res.unwrap().unwrap();
+        }
+
+        let logins = mock_server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.url.path() == "/api2/json/access/ticket")
+            .count();
+        assert_eq!(logins, 2);
+    }
+
+    #[tokio::test]
+    async fn test_ticket_renewal() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .and(body_string_contains("password=secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ticket": "first", "CSRFPreventionToken": "csrf" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // Renewal presents the current ticket as the password
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .and(body_string_contains("password=first"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ticket": "renewed", "CSRFPreventionToken": "csrf" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .and(header("Cookie", "PVEAuthCookie=renewed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let mut client = create_test_client(&mock_server.uri());
+        client.login("root@pam", "secret").await.unwrap();
+        client.set_ticket_renewal(std::time::Duration::ZERO);
+
+        let nodes = client.get_nodes().await.unwrap();
+        assert_eq!(nodes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_login_retried_on_next_call() {
+        let mock_server = MockServer::start().await;
+
+        // PVE rejects the first two logins, e.g. while its auth backend is down
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("auth backend down"))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/access/ticket"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "ticket": "late", "CSRFPreventionToken": "csrf" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .and(header("Cookie", "PVEAuthCookie=late"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let mut client = create_test_client(&mock_server.uri());
+        assert!(client.login("root@pam", "secret").await.is_err());
+        assert!(client.login_pending());
+
+        // Each call logs in first and reports the failure if it persists
+        assert!(client.get_nodes().await.is_err());
+        let nodes = client.get_nodes().await.unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert!(!client.login_pending());
+    }
+
+    #[tokio::test]
+    async fn test_multi_cluster_routing() {
+        let prod = MockServer::start().await;
+        let lab = MockServer::start().await;
+
+        for (server, node) in [(&prod, "pve-prod"), (&lab, "pve-lab")] {
+            Mock::given(method("GET"))
+                .and(path("/api2/json/nodes"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [{ "node": node }]
+                })))
+                .mount(server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&lab)
+            .await;
+
+        let clients = std::collections::BTreeMap::from([
+            ("prod".to_string(), create_test_client(&prod.uri())),
+            ("lab".to_string(), create_test_client(&lab.uri())),
+        ]);
+        let server = McpServer::with_clusters(clients, "prod", false).unwrap();
+
+        // Default cluster
+        let res = server.call_tool("list_nodes", &json!({})).await.unwrap();
+        assert!(res["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("pve-prod"));
+
+        // Explicit cluster
+        let res = server
+            .call_tool("list_nodes", &json!({ "cluster": "lab" }))
+            .await
+            .unwrap();
+        assert!(res["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("pve-lab"));
+
+        // Unknown cluster
+        let res = server
+            .call_tool("list_nodes", &json!({ "cluster": "staging" }))
+            .await;
+        assert!(res.is_err());
+
+        // Errors are tagged with the cluster name
+        let err = server
+            .call_tool("list_vms", &json!({ "cluster": "lab" }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::mcp::ClusterContext>().unwrap().0,
+            "lab"
+        );
+        assert!(err.downcast_ref::<crate::proxmox::ProxmoxError>().is_some());
+
+        // list_clusters
+        let res = server.call_tool("list_clusters", &json!({})).await.unwrap();
+        let clusters: serde_json::Value =
+            serde_json::from_str(res["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(clusters.as_array().unwrap().len(), 2);
+        assert!(clusters
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|c| c["name"] == "prod" && c["default"] == true));
+
+        // Every tool accepts a cluster argument
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/list".to_string(),
+            params: None,
+            id: Some(json!(1)),
+        };
+        let res = server.handle_request(req).await.unwrap();
+        let tools = res["tools"].as_array().unwrap();
+        let start_vm = tools.iter().find(|t| t["name"] == "start_vm").unwrap();
+        assert_eq!(
+            start_vm["inputSchema"]["properties"]["cluster"]["enum"],
+            json!(["lab", "prod"])
+        );
+
+        // Resources are listed per cluster
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "resources/list".to_string(),
+            params: None,
+            id: Some(json!(2)),
+        };
+        let res = server.handle_request(req).await.unwrap();
+        let resources = res["resources"].as_array().unwrap();
+        assert!(resources
+            .iter()
+            .any(|r| r["uri"] == "proxmox://vms?cluster=lab"));
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_next_node() {
+        let secondary = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve2" }]
+            })))
+            .mount(&secondary)
+            .await;
+
+        // Reserve a port with nothing listening on it for the primary
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let client = ProxmoxClient::new("http://127.0.0.1", port, true).unwrap();
+        client
+            .add_failover_host(secondary.uri().trim_start_matches("http://"))
+            .unwrap();
+
+        let nodes = client.get_nodes().await.unwrap();
+        assert_eq!(nodes[0].node, "pve2");
+
+        let health = client.endpoint_health();
+        assert!(!health[0].healthy);
+        assert!(health[1].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_server_error() {
+        let primary = MockServer::start().await;
+        let secondary = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("unavailable"))
+            .mount(&primary)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("bad gateway"))
+            .mount(&primary)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve2" }]
+            })))
+            .mount(&secondary)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(0)
+            .mount(&secondary)
+            .await;
+
+        let client = create_test_client(&primary.uri());
+        client
+            .add_failover_host(secondary.uri().trim_start_matches("http://"))
+            .unwrap();
+
+        // Reads fail over on 5xx
+        let nodes = client.get_nodes().await.unwrap();
+        assert_eq!(nodes[0].node, "pve2");
+
+        // Mutating calls are not re-sent to another node after a 5xx
+        let client = create_test_client(&primary.uri());
+        client
+            .add_failover_host(secondary.uri().trim_start_matches("http://"))
+            .unwrap();
+        let res = client.vm_action("pve1", 100, "start", None).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_no_failover_on_pve_error() {
+        let primary = MockServer::start().await;
+        let secondary = MockServer::start().await;
+
+        // PVE reports ordinary errors as 500s; the host itself is fine
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "data": null,
+                "message": "Configuration file 'nodes/pve1/qemu-server/100.conf' does not exist\n"
+            })))
+            .expect(1)
+            .mount(&primary)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": {} })))
+            .expect(0)
+            .mount(&secondary)
+            .await;
+
+        let client = create_test_client(&primary.uri());
+        client
+            .add_failover_host(secondary.uri().trim_start_matches("http://"))
+            .unwrap();
+
+        let res = client.get_vm_config("pve1", 100, "qemu").await;
+        assert!(res.is_err());
+        assert!(client.endpoint_health()[0].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_failures() {
+        let mock_server = MockServer::start().await;
+
+        // A pmxcfs lock timeout, then rate limiting, then success
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("got timeout"))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .with_priority(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1" }]
+            })))
+            .with_priority(3)
+            .mount(&mock_server)
+            .await;
+        // PVE may have acted on a mutating call before failing
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // Logical errors are not retried
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("no such VM"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client = create_test_client(&mock_server.uri());
+        client.set_retry_policy(crate::proxmox::retry::RetryPolicy {
+            base_delay_ms: 1,
+            ..Default::default()
+        });
+        let server = McpServer::new(client.clone(), false);
+
+        let nodes = client.get_nodes().await.unwrap();
+        assert_eq!(nodes[0].node, "pve1");
+        assert!(client.vm_action("pve1", 100, "start", None).await.is_err());
+        assert!(client.get_vm_config("pve1", 100, "qemu").await.is_err());
+
+        let metrics = server.metrics();
+        assert!(
+            metrics.contains("proxmox_api_retries_total{cluster=\"default\",method=\"GET\"} 2\n")
+        );
+        assert!(
+            metrics.contains("proxmox_api_requests_total{cluster=\"default\",method=\"GET\"} 4\n")
+        );
+        assert!(!metrics.contains("proxmox_api_retries_total{cluster=\"default\",method=\"POST\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_connection_failures() {
+        // Reserve a port with nothing listening on it
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let mut client = ProxmoxClient::new("http://127.0.0.1", port, true).unwrap();
+        client.set_retry_policy(crate::proxmox::retry::RetryPolicy {
+            base_delay_ms: 1,
+            ..Default::default()
+        });
+        let server = McpServer::new(client.clone(), false);
+
+        // A request that never left is retried even if it mutates
+        assert!(client.vm_action("pve1", 100, "start", None).await.is_err());
+        let metrics = server.metrics();
+        assert!(
+            metrics.contains("proxmox_api_requests_total{cluster=\"default\",method=\"POST\"} 3\n")
+        );
+        assert!(metrics.contains(
+            "proxmox_api_retries_exhausted_total{cluster=\"default\",method=\"POST\"} 1\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_queue_timeout() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "data": [{ "node": "pve1" }] }))
+                    .set_delay(std::time::Duration::from_millis(300)),
+            )
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        let mut client = create_test_client(&mock_server.uri());
+        client.set_cache(crate::proxmox::cache::CacheSettings {
+            enabled: false,
+            ..Default::default()
+        });
+        client.set_rate_limit(crate::proxmox::limits::RateLimit {
+            max_in_flight: 1,
+            queue_timeout_ms: 100,
+            ..Default::default()
+        });
+        let server = McpServer::new(client.clone(), false);
+
+        // The second request finds the only slot taken for longer than it may wait
+        let (first, second) = tokio::join!(client.get_nodes(), client.get_nodes());
+        assert!(first.is_ok());
+        let err = second.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::proxmox::ProxmoxError>(),
+            Some(crate::proxmox::ProxmoxError::Timeout(_))
+        ));
+
+        // With a longer queue timeout it waits its turn
+        client.set_rate_limit(crate::proxmox::limits::RateLimit {
+            max_in_flight: 1,
+            ..Default::default()
+        });
+        let (first, second) = tokio::join!(client.get_nodes(), client.get_nodes());
+        assert!(first.is_ok() && second.is_ok());
+
+        let metrics = server.metrics();
+        assert!(metrics
+            .contains("proxmox_api_queue_timeouts_total{cluster=\"default\",method=\"GET\"} 1\n"));
+        assert!(!metrics.contains("proxmox_api_retries_total{"));
+    }
+
+    #[tokio::test]
+    async fn test_response_cache() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "type": "qemu", "vmid": 100, "node": "pve1", "status": "stopped" }]
+            })))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:pve1" })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, false);
+
+        // Repeated reads are answered from the cache
+        server.call_tool("list_vms", &json!({})).await.unwrap();
+        server.call_tool("list_vms", &json!({})).await.unwrap();
+        server
+            .call_tool("list_containers", &json!({}))
+            .await
+            .unwrap();
+
+        // Unless the caller asks for current data
+        server
+            .call_tool("list_vms", &json!({ "no_cache": true }))
+            .await
+            .unwrap();
+
+        // A mutating call drops what it may have changed
+        server
+            .call_tool("start_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap();
+        server.call_tool("list_vms", &json!({})).await.unwrap();
+
+        assert!(server
+            .metrics()
+            .contains("proxmox_api_cache_hits_total{cluster=\"default\",method=\"GET\"} 2\n"));
+    }
+
+    #[tokio::test]
+    async fn test_log_messages_stay_in_their_session() {
+        use tracing_subscriber::layer::{Layer, SubscriberExt};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1", "status": "online" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let log_sessions = std::sync::Arc::new(crate::logging::LogSessions::default());
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_log_sessions(log_sessions.clone());
+
+        // The client's `log` records reach tracing through the bridge
+        let _ = tracing_log::LogTracer::init();
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(
+                crate::logging::McpLogLayer::new(log_sessions)
+                    .with_filter(crate::logging::McpLogLayer::filter()),
+            ),
+        );
+
+        let session = |id: &str| {
+            let (notifier, rx) = tokio::sync::mpsc::unbounded_channel();
+            let ctx = crate::mcp::RequestContext {
+                transport: crate::mcp::Transport::StreamableHttp,
+                session_id: Some(id.to_string()),
+                session_notifier: Some(notifier),
+                ..Default::default()
+            };
+            (ctx, rx)
+        };
+        let request = |method: &str, params: serde_json::Value| crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: Some(params),
+            id: Some(json!(1)),
+        };
+        let (ctx_a, mut rx_a) = session("a");
+        let (ctx_b, mut rx_b) = session("b");
+        for ctx in [&ctx_a, &ctx_b] {
+            server
+                .handle_request_with_context(
+                    request("logging/setLevel", json!({ "level": "debug" })),
+                    ctx,
+                )
+                .await
+                .unwrap();
+        }
+
+        server
+            .handle_request_with_context(
+                request(
+                    "tools/call",
+                    json!({ "name": "list_nodes", "arguments": {} }),
+                ),
+                &ctx_a,
+            )
+            .await
+            .unwrap();
+
+        let mut messages = Vec::new();
+        while let Ok(msg) = rx_a.try_recv() {
+            messages.push(
+                msg["params"]["data"]["message"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("PVE API request: GET nodes")));
+        assert!(rx_b.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_discover_endpoints() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "type": "cluster", "name": "lab" },
+                    { "type": "node", "name": "pve1", "ip": "127.0.0.1" },
+                    { "type": "node", "name": "pve2", "ip": "10.0.0.2" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        // pve1 is the host we are already connected to
+        assert_eq!(client.discover_endpoints().await.unwrap(), 1);
+        let health = client.endpoint_health();
+        assert_eq!(health.len(), 2);
+        assert!(health[1].url.contains("10.0.0.2"));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1" }]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api2/json/nodes/pve1/qemu/100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_read_only(true);
+
+        // Write tools are hidden
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/list".to_string(),
+            params: None,
+            id: Some(json!(1)),
+        };
+        let res = server.handle_request(req).await.unwrap();
+        let tools = res["tools"].as_array().unwrap();
+        assert!(tools.iter().any(|t| t["name"] == "list_vms"));
+        assert!(!tools.iter().any(|t| t["name"] == "delete_vm"));
+        assert!(!tools.iter().any(|t| t["name"] == "manage_service"));
+        assert!(tools
+            .iter()
+            .all(|t| t["annotations"]["readOnlyHint"] == true));
+
+        // ...and refused
+        let err = server
+            .call_tool("delete_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap_err();
+        let mcp_err = err.downcast_ref::<crate::mcp::McpError>().unwrap();
+        assert_eq!(mcp_err.code(), -32005);
+
+        // Read tools keep working
+        let res = server.call_tool("list_nodes", &json!({})).await.unwrap();
+        assert!(res["content"][0]["text"].as_str().unwrap().contains("pve1"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_policy() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api2/json/nodes/pve1/qemu/100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_policy(crate::policy::ToolPolicy {
+            allow: vec!["list_*".into(), "start_vm".into(), "delete_*".into()],
+            deny: vec!["delete_vm".into()],
+        });
+
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/list".to_string(),
+            params: None,
+            id: Some(json!(1)),
+        };
+        let res = server.handle_request(req).await.unwrap();
+        let names: Vec<&str> = res["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"start_vm"));
+        assert!(names.contains(&"list_vms"));
+        assert!(names.contains(&"delete_snapshot"));
+        assert!(names.contains(&"describe_policy"));
+        assert!(!names.contains(&"delete_vm"));
+        assert!(!names.contains(&"stop_vm"));
+
+        let err = server
+            .call_tool("delete_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap_err();
+        let mcp_err = err.downcast_ref::<crate::mcp::McpError>().unwrap();
+        assert_eq!(mcp_err.code(), -32006);
+
+        server
+            .call_tool("start_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap();
+
+        let res = server
+            .call_tool("describe_policy", &json!({}))
+            .await
+            .unwrap();
+        let policy: serde_json::Value =
+            serde_json::from_str(res["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(policy["deny"], json!(["delete_vm"]));
+        let permitted = policy["permitted_tools"].as_array().unwrap();
+        assert!(permitted.contains(&json!("start_vm")));
+        assert!(!permitted.contains(&json!("delete_vm")));
+    }
+
+    #[tokio::test]
+    async fn test_resource_scope() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "vmid": 100, "node": "pve1", "type": "qemu", "status": "running", "pool": "team-a", "tags": "web" },
+                    { "vmid": 101, "node": "pve1", "type": "lxc", "status": "stopped", "pool": "team-a" },
+                    { "vmid": 200, "node": "pve1", "type": "qemu", "status": "running", "pool": "team-b" },
+                    { "node": "pve1", "type": "node", "status": "online" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/stop"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/200/status/stop"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/storage/local/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "volid": "local:backup/vzdump-qemu-100.vma", "vmid": 100 },
+                    { "volid": "local:backup/vzdump-qemu-200.vma", "vmid": 200 }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_scope(crate::scope::ResourceScope {
+            pools: vec!["team-a".into()],
+            nodes: vec!["pve1".into()],
+            ..Default::default()
+        });
+
+        let res = server.call_tool("list_vms", &json!({})).await.unwrap();
+        let vms: Vec<serde_json::Value> =
+            serde_json::from_str(res["content"][0]["text"].as_str().unwrap()).unwrap();
+        let ids: Vec<i64> = vms.iter().map(|v| v["vmid"].as_i64().unwrap()).collect();
+        assert_eq!(ids, vec![100, 101]);
+
+        let res = server
+            .call_tool(
+                "list_backups",
+                &json!({ "node": "pve1", "storage": "local" }),
+            )
+            .await
+            .unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("qemu-100"));
+        assert!(!text.contains("qemu-200"));
+
+        server
+            .call_tool("stop_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap();
+
+        let err = server
+            .call_tool("stop_vm", &json!({ "node": "pve1", "vmid": 200 }))
+            .await
+            .unwrap_err();
+        let mcp_err = err.downcast_ref::<crate::mcp::McpError>().unwrap();
+        assert_eq!(mcp_err.code(), -32007);
+        assert!(err.to_string().contains("not in an allowed pool"));
+
+        let err = server
+            .call_tool("list_services", &json!({ "node": "pve2" }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Node 'pve2'"));
+
+        // New guests are checked against the pool they are created in
+        let err = server
+            .call_tool(
+                "create_vm",
+                &json!({ "node": "pve1", "vmid": 300, "pool": "team-b" }),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<crate::mcp::McpError>().is_some());
+
+        // Every guest a pool or ACL change names is checked
+        let err = server
+            .call_tool(
+                "update_pool",
+                &json!({ "poolid": "team-a", "vms": "101,200" }),
+            )
+            .await
+            .unwrap_err();
+        let mcp_err = err.downcast_ref::<crate::mcp::McpError>().unwrap();
+        assert_eq!(mcp_err.code(), -32007);
+        assert!(err.to_string().contains("200"));
+        let err = server
+            .call_tool(
+                "update_acl",
+                &json!({ "path": "/vms/200", "roles": "PVEVMAdmin" }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::mcp::McpError>().unwrap().code(),
+            -32007
+        );
+    }
+
+    #[tokio::test]
+    async fn test_guest_guard() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "vmid": 100, "node": "pve1", "type": "qemu", "status": "running" },
+                    { "vmid": 101, "node": "pve1", "type": "qemu", "status": "running" },
+                    { "vmid": 102, "node": "pve1", "type": "qemu", "status": "running" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "name": "db", "tags": "prod;db" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/101/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "name": "ca", "protection": 1 }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/102/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "name": "scratch", "tags": "test" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api2/json/nodes/pve1/qemu/100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:100" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api2/json/nodes/pve1/qemu/101"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:101" })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api2/json/nodes/pve1/qemu/102"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:102" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_guard(crate::guard::GuestGuard {
+            tags: vec!["prod".into(), "protected".into()],
+            ..Default::default()
+        });
+
+        // Tagged guest is refused, and the override is ignored unless allowed
+        let err = server
+            .call_tool(
+                "delete_vm",
+                &json!({ "node": "pve1", "vmid": 100, "override_guard": true }),
+            )
+            .await
+            .unwrap_err();
+        let mcp_err = err.downcast_ref::<crate::mcp::McpError>().unwrap();
+        assert_eq!(mcp_err.code(), -32008);
+        assert!(err.to_string().contains("tagged 'prod'"));
+
+        // PVE protection flag is honored too
+        let err = server
+            .call_tool("delete_vm", &json!({ "node": "pve1", "vmid": 101 }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("protection flag"));
+
+        // Unguarded guests are not affected
+        server
+            .call_tool("delete_vm", &json!({ "node": "pve1", "vmid": 102 }))
+            .await
+            .unwrap();
+
+        server.set_guard(crate::guard::GuestGuard {
+            tags: vec!["prod".into()],
+            allow_override: true,
+            ..Default::default()
+        });
+        let err = server
+            .call_tool("delete_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("override_guard=true"));
+        server
+            .call_tool(
+                "delete_vm",
+                &json!({ "node": "pve1", "vmid": 100, "override_guard": true }),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_tokens() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "vmid": 100, "node": "pve1", "type": "qemu", "status": "stopped", "name": "old-web", "maxdisk": 34359738368u64 }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/config"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "name": "old-web", "memory": 2048 }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api2/json/nodes/pve1/qemu/100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:100" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.enable_confirmation(std::time::Duration::from_secs(60));
+
+        let args = json!({ "node": "pve1", "vmid": 100 });
+
+        // First call only previews
+        let res = server.call_tool("delete_vm", &args).await.unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("NOT been executed"));
+        assert!(text.contains("old-web"));
+        assert!(text.contains("34359738368"));
+        let token = text
+            .split("\"confirmation_token\": \"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap()
+            .to_string();
+
+        // The token is bound to the arguments it was issued for
+        let err = server
+            .call_tool(
+                "delete_vm",
+                &json!({ "node": "pve1", "vmid": 101, "confirmation_token": token }),
+            )
+            .await
+            .unwrap_err();
+        let mcp_err = err.downcast_ref::<crate::mcp::McpError>().unwrap();
+        assert_eq!(mcp_err.code(), -32009);
+
+        // ...and spent by the mismatched attempt, so get a fresh one
+        let res = server.call_tool("delete_vm", &args).await.unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        let token = text
+            .split("\"confirmation_token\": \"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap()
+            .to_string();
+
+        let mut confirmed = args.clone();
+        confirmed["confirmation_token"] = json!(token);
+        let res = server.call_tool("delete_vm", &confirmed).await.unwrap();
+        assert!(res["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("UPID:100"));
+
+        // Tokens are single use
+        assert!(server.call_tool("delete_vm", &confirmed).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_preview_shows_definitions() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/pools/dev"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "comment": "Dev guests", "members": [{ "vmid": 100, "type": "qemu" }] }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/access/users"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "userid": "alice@pve", "email": "alice@example.com" },
+                    { "userid": "bob@pve" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": null })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.enable_confirmation(std::time::Duration::from_secs(60));
+
+        let res = server
+            .call_tool("delete_pool", &json!({ "poolid": "dev" }))
+            .await
+            .unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("NOT been executed"));
+        assert!(text.contains("Dev guests"));
+        assert!(text.contains("\"vmid\": 100"));
+
+        let res = server
+            .call_tool("delete_user", &json!({ "userid": "alice@pve" }))
+            .await
+            .unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("alice@example.com"));
+        assert!(!text.contains("bob@pve"));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": null })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": "UPID:..." })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "node": "pve1" }]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+
+        let res = server
+            .call_tool(
+                "add_disk",
+                &json!({
+                    "node": "pve1", "vmid": 100, "device": "scsi1",
+                    "storage": "local-lvm", "size_gb": 32, "format": "raw",
+                    "dry_run": true
+                }),
+            )
+            .await
+            .unwrap();
+        let plan: serde_json::Value =
+            serde_json::from_str(res["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(plan["dry_run"], true);
+        assert_eq!(
+            plan["requests"],
+            json!([{
+                "method": "PUT",
+                "path": "/api2/json/nodes/pve1/qemu/100/config",
+                "body": { "scsi1": "local-lvm:32,format=raw" }
+            }])
+        );
+
+        // With the global setting every mutating call is planned only, while
+        // read-only tools still run
+        server.set_dry_run(true);
+        let res = server
+            .call_tool("start_vm", &json!({ "node": "pve1", "vmid": 100 }))
+            .await
+            .unwrap();
+        let plan: serde_json::Value =
+            serde_json::from_str(res["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            plan["requests"][0]["path"],
+            "/api2/json/nodes/pve1/qemu/100/status/start"
+        );
+        let res = server.call_tool("list_nodes", &json!({})).await.unwrap();
+        assert!(res["content"][0]["text"].as_str().unwrap().contains("pve1"));
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": "UPID:pve1:0001:0002:65A0:qmstart:100:root@pam:"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_audit_log(
+            crate::audit::AuditLog::new(&crate::audit::AuditSettings {
+                dir: Some(dir.path().to_str().unwrap().to_string()),
+                rotate: Some("never".to_string()),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+
+        let ctx = crate::mcp::RequestContext {
+            transport: crate::mcp::Transport::Sse,
+            session_id: Some("session-1".to_string()),
+            caller: Some(crate::mcp::Caller {
+                remote_addr: Some("10.0.0.5:40000".to_string()),
+                auth: "bearer",
+            }),
+            notifier: None,
+            session_notifier: None,
+        };
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/call".to_string(),
+            params: Some(json!({
+                "name": "start_vm",
+                "arguments": { "node": "pve1", "vmid": 100 }
+            })),
+            id: Some(json!(1)),
+        };
+        server.handle_request_with_context(req, &ctx).await.unwrap();
+
+        // A dry run sends nothing to PVE and is not recorded as a success
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/call".to_string(),
+            params: Some(json!({
+                "name": "start_vm",
+                "arguments": { "node": "pve1", "vmid": 100, "dry_run": true }
+            })),
+            id: Some(json!(2)),
+        };
+        server.handle_request_with_context(req, &ctx).await.unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("audit.jsonl")).unwrap();
+        let records: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["outcome"], "dry_run");
+        let record = &records[0];
+        assert_eq!(record["transport"], "sse");
+        assert_eq!(record["session_id"], "session-1");
+        assert_eq!(record["caller"]["remote_addr"], "10.0.0.5:40000");
+        assert_eq!(record["caller"]["auth"], "bearer");
+        assert_eq!(record["tool"], "start_vm");
+        assert_eq!(record["arguments"]["vmid"], 100);
+        assert_eq!(
+            record["upid"],
+            "UPID:pve1:0001:0002:65A0:qmstart:100:root@pam:"
+        );
+        assert_eq!(record["outcome"], "success");
+        assert!(record["timestamp"].as_str().unwrap().contains('T'));
+    }
+
+    #[tokio::test]
+    async fn test_progress_notifications() {
+        let mock_server = MockServer::start().await;
+        let upid = "UPID:pve1:0001:0002:65A0:vzdump:100:root@pam:";
+        Mock::given(method("POST"))
+            .and(path("/api2/json/nodes/pve1/vzdump"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": upid })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}/status", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "status": "stopped", "exitstatus": "OK" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}/log", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "n": 1, "t": "INFO: starting new backup job: vzdump 100" },
+                    { "n": 2, "t": "INFO:  40% (12.8 GiB of 32.0 GiB) in 30s" },
+                    { "n": 3, "t": "INFO:  85% (27.2 GiB of 32.0 GiB) in 1m" },
+                    { "n": 4, "t": "INFO: backup finished" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, false);
+
+        let (notifier, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = crate::mcp::RequestContext {
+            notifier: Some(notifier),
+            ..Default::default()
+        };
+        let call = |meta: serde_json::Value| crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/call".to_string(),
+            params: Some(json!({
+                "name": "create_backup",
+                "arguments": { "node": "pve1", "vmid": 100 },
+                "_meta": meta
+            })),
+            id: Some(json!(1)),
+        };
+
+        let res = server
+            .handle_request_with_context(call(json!({ "progressToken": "backup-1" })), &ctx)
+            .await
+            .unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("Backup initiated"));
+        assert!(text.contains("Task finished with status: OK"));
+
+        let mut progress = Vec::new();
+        while let Ok(n) = rx.try_recv() {
+            assert_eq!(n["method"], "notifications/progress");
+            assert_eq!(n["params"]["progressToken"], "backup-1");
+            progress.push(n["params"]["progress"].as_f64().unwrap());
+        }
+        assert_eq!(progress, vec![40.0, 85.0, 100.0]);
+
+        // Without a token the tool returns as soon as the task is started
+        let res = server
+            .handle_request_with_context(call(json!({})), &ctx)
+            .await
+            .unwrap();
+        let text = res["content"][0]["text"].as_str().unwrap();
+        assert!(!text.contains("Task finished"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_cancellation() {
+        let mock_server = MockServer::start().await;
+        let upid = "UPID:pve1:0001:0002:65A0:vzdump:100:root@pam:";
+        Mock::given(method("GET"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}/status", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "status": "running" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": null })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_stop_task_on_cancel(true);
+
+        let ctx = crate::mcp::RequestContext {
+            session_id: Some("session-1".to_string()),
+            ..Default::default()
+        };
+        let wait = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/call".to_string(),
+            params: Some(json!({
+                "name": "wait_for_task",
+                "arguments": { "node": "pve1", "upid": upid, "timeout": 300 }
+            })),
+            id: Some(json!(7)),
+        };
+        let call = tokio::spawn({
+            let server = server.clone();
+            let ctx = ctx.clone();
+            async move { server.handle_request_with_context(wait, &ctx).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        // A cancellation from another session does not match the request
+        let cancel = |session: &str| {
+            let ctx = crate::mcp::RequestContext {
+                session_id: Some(session.to_string()),
+                ..Default::default()
+            };
+            let req = crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/cancelled".to_string(),
+                params: Some(json!({ "requestId": 7, "reason": "no longer needed" })),
+                id: None,
+            };
+            let server = server.clone();
+            async move { server.handle_request_with_context(req, &ctx).await }
+        };
+        cancel("session-2").await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!call.is_finished());
+
+        cancel("session-1").await.unwrap();
+        let err = tokio::time::timeout(std::time::Duration::from_secs(5), call)
+            .await
+            .expect("cancelled call returns promptly")
+            .unwrap()
+            .unwrap_err();
+        assert!(crate::mcp::is_cancelled(&err));
+        assert!(err.to_string().contains("no longer needed"));
+
+        // A request registered before it is handed off cannot miss a
+        // cancellation that overtakes it
+        server.set_stop_task_on_cancel(false);
+        let wait = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/call".to_string(),
+            params: Some(json!({
+                "name": "wait_for_task",
+                "arguments": { "node": "pve1", "upid": upid, "timeout": 300 }
+            })),
+            id: Some(json!(8)),
+        };
+        let registration = server.register_request(&wait, &ctx);
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/cancelled".to_string(),
+            params: Some(json!({ "requestId": 8 })),
+            id: None,
+        };
+        server.handle_request_with_context(req, &ctx).await.unwrap();
+        let err = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            server.handle_registered_request(wait, &ctx, registration),
+        )
+        .await
+        .expect("cancelled call returns promptly")
+        .unwrap_err();
+        assert!(crate::mcp::is_cancelled(&err));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_does_not_stop_task_when_not_permitted() {
+        let mock_server = MockServer::start().await;
+        let upid = "UPID:pve1:0001:0002:65A0:vzdump:100:root@pam:";
+        Mock::given(method("GET"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}/status", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "status": "running" }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "type": "qemu", "vmid": 100, "node": "pve1", "status": "running" }]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": null })))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let wait_and_cancel = |server: McpServer| async move {
+            let ctx = crate::mcp::RequestContext::default();
+            let wait = crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "tools/call".to_string(),
+                params: Some(json!({
+                    "name": "wait_for_task",
+                    "arguments": { "node": "pve1", "upid": upid, "timeout": 300 }
+                })),
+                id: Some(json!(1)),
+            };
+            let call = tokio::spawn({
+                let (server, ctx) = (server.clone(), ctx.clone());
+                async move { server.handle_request_with_context(wait, &ctx).await }
+            });
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            let cancel = crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/cancelled".to_string(),
+                params: Some(json!({ "requestId": 1 })),
+                id: None,
+            };
+            server
+                .handle_request_with_context(cancel, &ctx)
+                .await
+                .unwrap();
+            let err = call.await.unwrap().unwrap_err();
+            assert!(crate::mcp::is_cancelled(&err));
+        };
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_stop_task_on_cancel(true);
+
+        // wait_for_task is read-only, but stopping the task is not
+        let mut read_only = server.clone();
+        read_only.set_read_only(true);
+        wait_and_cancel(read_only).await;
+
+        let mut dry_run = server.clone();
+        dry_run.set_dry_run(true);
+        wait_and_cancel(dry_run).await;
+
+        let mut denied = server.clone();
+        denied.set_policy(crate::policy::ToolPolicy {
+            deny: vec!["stop_*".into()],
+            ..Default::default()
+        });
+        wait_and_cancel(denied).await;
+
+        // The task belongs to guest 100, which is out of scope
+        let mut scoped = server.clone();
+        scoped.set_scope(crate::scope::ResourceScope {
+            vmids: vec![crate::scope::VmidRange::try_from("200".to_string()).unwrap()],
+            ..Default::default()
+        });
+        wait_and_cancel(scoped).await;
+    }
+
+    #[tokio::test]
+    async fn test_stdio_concurrent_requests() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let mock_server = MockServer::start().await;
+        let upid = "UPID:pve1:0001:0002:65A0:vzdump:100:root@pam:";
+        Mock::given(method("GET"))
+            .and(path(format!("/api2/json/nodes/pve1/tasks/{}/status", upid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "status": "running" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, true);
+
+        let (mut input, server_input) = tokio::io::duplex(4096);
+        let (server_output, output) = tokio::io::duplex(4096);
+        let serving =
+            tokio::spawn(async move { server.serve_stdio(server_input, server_output).await });
+        let mut lines = BufReader::new(output).lines();
+
+        let send = |msg: serde_json::Value| format!("{}\n", msg);
+        let wait = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+            "params": { "name": "wait_for_task", "arguments": { "node": "pve1", "upid": upid, "timeout": 300 } }
+        });
+        input.write_all(send(wait).as_bytes()).await.unwrap();
+        input
+            .write_all(send(json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" })).as_bytes())
+            .await
+            .unwrap();
+
+        // The ping is answered while the wait is still running
+        let line = lines.next_line().await.unwrap().unwrap();
+        let msg: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(msg["id"], 2);
+
+        let load = json!({
+            "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+            "params": { "name": "load_all_tools", "arguments": {} }
+        });
+        input.write_all(send(load).as_bytes()).await.unwrap();
+        let mut seen = Vec::new();
+        for _ in 0..2 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            let msg: serde_json::Value = serde_json::from_str(&line).unwrap();
+            seen.push(
+                msg["method"]
+                    .as_str()
+                    .map_or(msg["id"].to_string(), String::from),
+            );
+        }
+        seen.sort();
+        assert_eq!(seen, vec!["3", "notifications/tools/list_changed"]);
+
+        // Cancelling the wait ends it without a response
+        let cancel = json!({
+            "jsonrpc": "2.0", "method": "notifications/cancelled",
+            "params": { "requestId": 1 }
+        });
+        input.write_all(send(cancel).as_bytes()).await.unwrap();
+        drop(input);
+        tokio::time::timeout(std::time::Duration::from_secs(5), serving)
+            .await
+            .expect("server exits after EOF")
+            .unwrap()
+            .unwrap();
+        assert!(lines.next_line().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resource_subscriptions() {
+        let mock_server = MockServer::start().await;
+        let resources = |status: &str| {
+            ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "vmid": 100, "node": "pve1", "type": "qemu", "status": status },
+                    { "vmid": 101, "node": "pve2", "type": "qemu", "status": "running" }
+                ]
+            }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(resources("running"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(resources("stopped"))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let mut server = McpServer::new(client, false);
+        server.set_subscription_poll_interval(std::time::Duration::from_millis(50));
+
+        let (notifier, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = crate::mcp::RequestContext {
+            session_id: Some("session-1".to_string()),
+            session_notifier: Some(notifier),
+            ..Default::default()
+        };
+        let request = |method: &str, uri: &str| crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: Some(json!({ "uri": uri })),
+            id: Some(json!(1)),
+        };
+
+        for uri in ["proxmox://guest/100/config", "proxmox://node/pve2/status"] {
+            server
+                .handle_request_with_context(request("resources/subscribe", uri), &ctx)
+                .await
+                .unwrap();
+        }
+        assert!(server
+            .handle_request_with_context(
+                request("resources/subscribe", "proxmox://cluster/ha"),
+                &ctx
+            )
+            .await
+            .is_err());
+
+        // Guest 100 stops after the first poll; pve2 is untouched
+        let update = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .expect("update is sent")
+            .unwrap();
+        assert_eq!(update["method"], "notifications/resources/updated");
+        assert_eq!(update["params"]["uri"], "proxmox://guest/100/config");
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        assert!(rx.try_recv().is_err());
+
+        server
+            .handle_request_with_context(
+                request("resources/unsubscribe", "proxmox://guest/100/config"),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        server.end_session(Some("session-1"));
+        drop(ctx);
+        // With every subscription gone, nothing holds the notifier any more
+        tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            while rx.recv().await.is_some() {}
+        })
+        .await
+        .expect("notifier is released");
+    }
+
+    #[tokio::test]
+    async fn test_prompts() {
+        let mock_server = MockServer::start().await;
+        let client = create_test_client(&mock_server.uri());
+        let request =
+            |method: &str, params: Option<serde_json::Value>| crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params,
+                id: Some(json!(1)),
+            };
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("restart_web.toml"),
+            r#"
+description = "Restart the web tier"
+tools = ["reboot_vm"]
+template = "Reboot {vmid} and check it comes back."
+
+[[arguments]]
+name = "vmid"
+required = true
+"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a prompt").unwrap();
+        let mut prompts = crate::prompts::PromptLibrary::default();
+        assert_eq!(prompts.load_dir(dir.path()).unwrap(), 1);
+
+        let mut server = McpServer::new(client, false);
+        server.set_prompts(prompts);
+
+        let res = server
+            .handle_request(request("prompts/list", None))
+            .await
+            .unwrap();
+        let names: Vec<&str> = res["prompts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"diagnose_vm_start"));
+        assert!(names.contains(&"restart_web"));
+
+        let res = server
+            .handle_request(request(
+                "prompts/get",
+                Some(json!({ "name": "restart_web", "arguments": { "vmid": "100" } })),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res["messages"][0]["role"], "user");
+        assert_eq!(
+            res["messages"][0]["content"]["text"],
+            "Reboot 100 and check it comes back."
+        );
+
+        let res = server
+            .handle_request(request(
+                "prompts/get",
+                Some(json!({ "name": "restart_web" })),
+            ))
+            .await;
+        assert_eq!(crate::mcp::json_rpc_error(&res.unwrap_err()).code, -32602);
+
+        // Prompts relying on tools that may not be called are hidden
+        server.set_read_only(true);
+        let res = server
+            .handle_request(request("prompts/list", None))
+            .await
+            .unwrap();
+        let names: Vec<&str> = res["prompts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"audit_old_snapshots"));
+        assert!(!names.contains(&"restart_web"));
+        assert!(!names.contains(&"prepare_node_maintenance"));
+        let res = server
+            .handle_request(request(
+                "prompts/get",
+                Some(json!({ "name": "restart_web", "arguments": { "vmid": "100" } })),
+            ))
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_completion() {
+        let mock_server = MockServer::start().await;
+        // Cached: fetched once for all the completions below
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "node": "pve1", "status": "online" },
+                    { "node": "pve2", "status": "online" },
+                    { "node": "pve3", "status": "offline" }
+                ]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/cluster/resources"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "vmid": 101, "node": "pve1", "type": "qemu", "status": "running", "name": "db" },
+                    { "vmid": 100, "node": "pve1", "type": "qemu", "status": "running", "name": "web" },
+                    { "vmid": 200, "node": "pve2", "type": "lxc", "status": "stopped", "name": "dns" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        for node in ["pve1", "pve2"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/api2/json/nodes/{}/storage", node)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [{ "storage": "local" }, { "storage": format!("{}-zfs", node) }]
+                })))
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/network"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "iface": "eno1", "type": "eth" },
+                    { "iface": "vmbr0", "type": "bridge" }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api2/json/nodes/pve1/qemu/100/snapshot"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "name": "before-upgrade", "snaptime": 1700000000 }, { "name": "current" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_test_client(&mock_server.uri());
+        let server = McpServer::new(client, false);
+        let complete = |argument: &str, value: &str, context: serde_json::Value| {
+            let req = crate::mcp::JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "completion/complete".to_string(),
+                params: Some(json!({
+                    "ref": { "type": "ref/prompt", "name": "diagnose_vm_start" },
+                    "argument": { "name": argument, "value": value },
+                    "context": { "arguments": context }
+                })),
+                id: Some(json!(1)),
+            };
+            let server = server.clone();
+            async move { server.handle_request(req).await.unwrap()["completion"].clone() }
+        };
+
+        let nodes = complete("node", "pve", json!({})).await;
+        assert_eq!(nodes["values"], json!(["pve1", "pve2"]));
+        assert_eq!(nodes["hasMore"], false);
+
+        let vmids = complete("vmid", "10", json!({})).await;
+        assert_eq!(vmids["values"], json!(["100", "101"]));
+        let by_name = complete("vmid", "we", json!({})).await;
+        assert_eq!(by_name["values"], json!(["100"]));
+
+        let storage = complete("storage", "", json!({})).await;
+        assert_eq!(storage["values"], json!(["local", "pve1-zfs", "pve2-zfs"]));
+        let storage = complete("storage", "", json!({ "node": "pve1" })).await;
+        assert_eq!(storage["values"], json!(["local", "pve1-zfs"]));
+
+        let bridges = complete("bridge", "", json!({ "node": "pve1" })).await;
+        assert_eq!(bridges["values"], json!(["vmbr0"]));
+
+        let snapshots = complete("snapname", "", json!({ "vmid": 100 })).await;
+        assert_eq!(snapshots["values"], json!(["before-upgrade"]));
+
+        let unknown = complete("description", "", json!({})).await;
+        assert_eq!(unknown["values"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_every_tool_is_classified() {
+        let client = ProxmoxClient::new("localhost", 8006, true).unwrap();
+        let server = McpServer::new(client, false);
+
+        let req = crate::mcp::JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "tools/list".to_string(),
+            params: None,
+            id: Some(json!(1)),
+        };
+        let res = server.handle_request(req).await.unwrap();
+        let tools = res["tools"].as_array().unwrap();
+        for tool in tools {
+            for hint in [
+                "readOnlyHint",
+                "destructiveHint",
+                "idempotentHint",
+                "openWorldHint",
+            ] {
+                assert!(
+                    tool["annotations"][hint].is_boolean(),
+                    "{} has no {}",
+                    tool["name"],
+                    hint
+                );
+            }
+            if let Some(schema) = tool.get("outputSchema") {
+                assert_eq!(schema["type"], "object", "{}", tool["name"]);
+            }
+        }
+
+        // The annotation tables only name existing write tools
+        use crate::annotations::{DESTRUCTIVE_TOOLS, IDEMPOTENT_TOOLS, OPEN_WORLD_TOOLS};
+        let writes = |name: &&str| {
+            tools
+                .iter()
+                .any(|t| t["name"] == *name && t["annotations"]["readOnlyHint"] == false)
+        };
+        assert!(DESTRUCTIVE_TOOLS.iter().all(writes));
+        assert!(IDEMPOTENT_TOOLS.iter().all(writes));
+        assert!(OPEN_WORLD_TOOLS
+            .iter()
+            .all(|n| tools.iter().any(|t| t["name"] == *n)));
+
+        // Every tool with an outputSchema returns the declared key
+        let mock_server = MockServer::start().await;
+        let upid = "UPID:pve1:00001234:00005678:65432100:qmstart:100:root@pam:";
+        let responses = [
+            ("/api2/json/nodes", json!([{ "node": "pve1" }])),
+            (
+                "/api2/json/cluster/resources",
+                json!([{ "vmid": 100, "node": "pve1", "status": "running", "type": "lxc" }]),
+            ),
+            (
+                "/api2/json/nodes/pve1/qemu/100/snapshot",
+                json!([{ "name": "current" }]),
+            ),
+            (
+                "/api2/json/nodes/pve1/storage",
+                json!([{ "storage": "local" }]),
+            ),
+            ("/api2/json/nodes/pve1/tasks", json!([{ "upid": upid }])),
+            (
+                &format!("/api2/json/nodes/pve1/tasks/{}/status", upid),
+                json!({ "status": "stopped", "exitstatus": "OK" }),
+            ),
+        ];
+        for (p, data) in responses {
+            Mock::given(method("GET"))
+                .and(path(p))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": data })))
+                .mount(&mock_server)
+                .await;
+        }
+        let server = McpServer::new(create_test_client(&mock_server.uri()), false);
+        let node = json!({ "node": "pve1" });
+        let task = json!({ "node": "pve1", "upid": upid });
+        for tool in tools.iter().filter(|t| t.get("outputSchema").is_some()) {
+            let name = tool["name"].as_str().unwrap();
+            let args = match name {
+                "list_nodes" | "list_vms" | "list_containers" => json!({}),
+                "list_snapshots" => json!({ "node": "pve1", "vmid": 100 }),
+                "list_storage" | "list_tasks" => node.clone(),
+                "get_task_status" | "wait_for_task" => task.clone(),
+                _ => panic!("{} has an outputSchema but no test call", name),
+            };
+            let res = server.call_tool(name, &args).await.unwrap();
+            for key in tool["outputSchema"]["required"].as_array().unwrap() {
+                let key = key.as_str().unwrap();
+                assert!(
+                    res["structuredContent"].get(key).is_some(),
+                    "{} returned no {}",
+                    name,
+                    key
+                );
+            }
+        }
+    }
 }
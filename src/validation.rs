@@ -0,0 +1,104 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Whether `value` is of the JSON Schema type `ty`. Integers must not have
+/// a fractional part; types this server does not use are accepted.
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Checks tool arguments against the tool's `inputSchema` and returns what
+/// is wrong with each offending argument: required arguments that are
+/// missing and arguments of the wrong type. Only `required`, `type` and the
+/// `items` type of arrays are checked; PVE validates the values themselves.
+pub fn validate(schema: &Value, args: &Value) -> BTreeMap<String, String> {
+    let mut errors = BTreeMap::new();
+    let empty = serde_json::Map::new();
+    let args = match args {
+        Value::Object(args) => args,
+        Value::Null => &empty,
+        _ => {
+            errors.insert(String::new(), "arguments must be an object".to_string());
+            return errors;
+        }
+    };
+
+    let required = schema["required"].as_array().into_iter().flatten();
+    for field in required.filter_map(|f| f.as_str()) {
+        if args.get(field).is_none_or(|v| v.is_null()) {
+            errors.insert(field.to_string(), "is required".to_string());
+        }
+    }
+
+    for (field, value) in args.iter().filter(|(_, v)| !v.is_null()) {
+        let property = &schema["properties"][field];
+        if let Some(problem) = type_error(property, value) {
+            errors.insert(field.clone(), problem);
+        } else if let (Some(items), Some(values)) = (property.get("items"), value.as_array()) {
+            if let Some((i, problem)) = values
+                .iter()
+                .enumerate()
+                .find_map(|(i, v)| type_error(items, v).map(|p| (i, p)))
+            {
+                errors.insert(format!("{}[{}]", field, i), problem);
+            }
+        }
+    }
+    errors
+}
+
+fn type_error(property: &Value, value: &Value) -> Option<String> {
+    let types: Vec<&str> = match &property["type"] {
+        Value::String(ty) => vec![ty.as_str()],
+        Value::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+        _ => return None,
+    };
+    if types.iter().any(|ty| has_type(value, ty)) {
+        return None;
+    }
+    Some(format!("must be of type {}", types.join(" or ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "node": { "type": "string" },
+                "vmid": { "type": "integer" },
+                "memory": { "type": ["integer", "string"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["node", "vmid"]
+        });
+        assert!(validate(&schema, &json!({ "node": "pve1", "vmid": 100 })).is_empty());
+
+        // Every problem is reported at once
+        let errors = validate(
+            &schema,
+            &json!({ "vmid": "100", "memory": 1.5, "tags": ["a", 1], "extra": 1 }),
+        );
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors["node"], "is required");
+        assert_eq!(errors["vmid"], "must be of type integer");
+        assert_eq!(errors["memory"], "must be of type integer or string");
+        assert_eq!(errors["tags[1]"], "must be of type string");
+
+        assert_eq!(validate(&schema, &Value::Null).len(), 2);
+        assert!(validate(&schema, &json!([1])).contains_key(""));
+    }
+}